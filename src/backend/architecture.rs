@@ -12,10 +12,15 @@ use binaryninja::{
 
 use crate::{
 	decoder::{
+		encoding::Encoding,
 		inst::Inst,
-		opcode::{OpType, Opcode},
+		opcode::{OpType, Opcode, LATEST_VERSION},
+		operand::CaptureKind,
+	},
+	file::{
+		data::{Function, Module, Str},
+		registry::{self, Entry},
 	},
-	file::view::MODULE,
 };
 
 use super::{
@@ -23,6 +28,11 @@ use super::{
 	text_builder::TextBuilder,
 };
 
+pub const ARCH_NAME: &str = "luau";
+
+// Undecodable bytes are skipped a word at a time so analysis can carry on past them
+const INVALID_LEN: usize = 4;
+
 pub struct Architecture {
 	pub handle: CustomArchitectureHandle<Self>,
 	pub core: CoreArchitecture,
@@ -35,9 +45,9 @@ impl Architecture {
 
 	fn get_opt_instruction_info(decoder: Inst, addr: u64) -> InstructionInfo {
 		let op = decoder.op();
-		let next = op.len() as i64 / 4 - 1;
+		let next = op.size() as i64 / 4 - 1;
 
-		let mut info = InstructionInfo::new(op.len(), false);
+		let mut info = InstructionInfo::new(op.size(), false);
 
 		match op {
 			Opcode::LoadBoolean => {
@@ -89,16 +99,16 @@ impl Architecture {
 				let mut on_false = Inst::get_jump_target(addr, next);
 				let mut on_true = Inst::get_jump_target(addr, decoder.d());
 
-				if decoder.adjacent() < 0 {
+				if decoder.adjacent().is_some_and(|v| v < 0) {
 					std::mem::swap(&mut on_false, &mut on_true);
 				}
 
 				info.add_branch(BranchInfo::False(on_false), None);
 				info.add_branch(BranchInfo::True(on_true), None);
 			}
-			Opcode::FastCall | Opcode::FastCall1 | Opcode::FastCall2 | Opcode::FastCall2K => {
+			op if op.is_fast_call() => {
 				let on_false = Inst::get_jump_target(addr, next);
-				let on_true = Inst::get_jump_target(addr, decoder.c() + 1);
+				let on_true = decoder.location_target(addr, decoder.c().into());
 
 				info.add_branch(BranchInfo::Indirect, None);
 				info.add_branch(BranchInfo::False(on_false), None);
@@ -110,45 +120,106 @@ impl Architecture {
 		info
 	}
 
-	fn get_opt_instruction_text(decoder: Inst, addr: u64) -> Option<TextBuilder> {
+	fn add_register(
+		builder: &mut TextBuilder,
+		raw: i32,
+		offset: u64,
+		func: Option<&Function>,
+		module: Option<&Module>,
+	) -> Option<()> {
+		let register = raw.try_into().ok()?;
+		let name = func
+			.and_then(|func| func.local_at(register, offset))
+			.zip(module)
+			.and_then(|(local, module)| module.string_at(local.name))
+			.map(Str::to_string_lossy);
+
+		builder.add_register(register, name.as_deref());
+
+		Some(())
+	}
+
+	fn add_upvalue(
+		builder: &mut TextBuilder,
+		raw: i32,
+		func: Option<&Function>,
+		module: Option<&Module>,
+	) -> Option<()> {
+		let upvalue = raw.try_into().ok()?;
+		let name = func
+			.and_then(|func| func.upvalue_at(upvalue))
+			.zip(module)
+			.and_then(|(name, module)| module.string_at(name))
+			.map(Str::to_string_lossy);
+
+		builder.add_upvalue(upvalue, name.as_deref());
+
+		Some(())
+	}
+
+	// Operands that index into the module are shown as plain integers when the lookup
+	// fails, including for code outside any Luau view, which has no module at all
+	fn get_opt_instruction_text(
+		decoder: Inst,
+		addr: u64,
+		version: u8,
+		entry: Option<&Entry>,
+	) -> Option<TextBuilder> {
 		let opcode = decoder.op();
+		let base = entry.map_or(0, Entry::base);
+		let offset = addr.wrapping_sub(base);
+		let module = entry.map(Entry::module);
+		let func = module.and_then(|module| module.by_address(offset));
 
-		let mut builder = TextBuilder::with_mnemonic(opcode);
+		let mut builder = TextBuilder::with_mnemonic(opcode, base);
 
 		for (name, typ) in opcode.iter_operands() {
-			let raw = decoder.with_name(name);
+			let raw = decoder.with_name(name)?;
 
 			match typ {
 				OpType::Location => builder.add_location(addr, raw.into()),
-				OpType::Register => builder.add_register(raw.try_into().ok()?),
-				OpType::UpValue => builder.add_upvalue(raw.try_into().ok()?),
+				OpType::Skip => {
+					let target = decoder.location_target(addr, raw.into());
+
+					builder.add_skip(raw, target);
+				}
+				OpType::Register => Self::add_register(&mut builder, raw, offset, func, module)?,
+				OpType::UpValue => Self::add_upvalue(&mut builder, raw, func, module)?,
+				OpType::Count(label) => builder.add_count(label, raw),
+				OpType::Size(label) => builder.add_size(label, raw),
+				OpType::HashSize => builder.add_hash_size(raw),
+				OpType::CaptureKind => builder.add_capture_kind(raw),
+				OpType::Not => builder.add_not(raw != 0),
+				OpType::CaptureIndex => match CaptureKind::from_raw(decoder.a().into()) {
+					Some(CaptureKind::UpValue) => {
+						Self::add_upvalue(&mut builder, raw, func, module)?
+					}
+					_ => Self::add_register(&mut builder, raw, offset, func, module)?,
+				},
 				OpType::Boolean => builder.add_boolean(raw != 0),
 				OpType::Integer => builder.add_integer(raw),
-				OpType::Constant => {
-					let module = MODULE.read().unwrap();
-					let func = module.by_address(addr)?;
+				OpType::Constant => builder.add_or_integer(raw, |builder| {
+					let (func, module) = func.zip(module)?;
 					let value = func.constant_list().data.get(raw as usize)?;
 
-					builder.add_constant(value, func, &module)?;
-				}
-				OpType::Function => {
-					let module = MODULE.read().unwrap();
+					builder.add_constant(value, func, module)
+				}),
+				OpType::Function => builder.add_or_integer(raw, |builder| {
+					let (func, module) = func.zip(module)?;
 					let global = &module.function_list();
-					let adjusted = module
-						.by_address(addr)?
-						.reference_list()
-						.data
-						.get(raw as usize)?;
-
-					builder.add_function(*adjusted, &global.data)?;
-				}
-				OpType::Import => {
-					let module = MODULE.read().unwrap();
-					let func = module.by_address(addr)?;
-
-					builder.add_import(raw as u32, func, &module)?;
-				}
-				OpType::BuiltIn => builder.add_built_in(raw.try_into().ok()?)?,
+					let adjusted = func.reference_list().data.get(raw as usize)?;
+
+					builder.add_function(*adjusted, &global.data)
+				}),
+				OpType::Import => builder.add_or_integer(raw, |builder| {
+					let (func, module) = func.zip(module)?;
+
+					builder.add_import(raw as u32, func, module)
+				}),
+				OpType::BuiltIn => match u8::try_from(raw) {
+					Ok(index) => builder.add_built_in(index, version),
+					Err(_) => builder.add_integer(raw),
+				},
 			}
 		}
 
@@ -200,10 +271,18 @@ impl BaseArchitecture for Architecture {
 	}
 
 	fn instruction_info(&self, data: &[u8], addr: u64) -> Option<InstructionInfo> {
-		let decoder = Inst::try_from(data).ok()?;
-		let info = Self::get_opt_instruction_info(decoder, addr);
+		let entry = registry::find(addr);
+		let (encoding, version) = entry.map_or((Encoding::Plain, LATEST_VERSION), |v| {
+			let format = v.module().format();
+
+			(format.encoding, format.version)
+		});
 
-		Some(info)
+		match Inst::decode(data, encoding, version) {
+			Ok(decoder) => Some(Self::get_opt_instruction_info(decoder, addr)),
+			Err(_) if data.len() >= INVALID_LEN => Some(InstructionInfo::new(INVALID_LEN, false)),
+			Err(_) => None,
+		}
 	}
 
 	fn instruction_text(
@@ -211,10 +290,29 @@ impl BaseArchitecture for Architecture {
 		data: &[u8],
 		addr: u64,
 	) -> Option<(usize, Vec<InstructionTextToken>)> {
-		let decoder = Inst::try_from(data).ok()?;
-		let builder = Self::get_opt_instruction_text(decoder, addr)?;
+		let entry = registry::find(addr);
+		let (encoding, version) = entry
+			.as_deref()
+			.map_or((Encoding::Plain, LATEST_VERSION), |v| {
+				let format = v.module().format();
 
-		Some((decoder.op().len(), builder.into()))
+				(format.encoding, format.version)
+			});
+
+		match Inst::decode(data, encoding, version) {
+			Ok(decoder) => {
+				let builder =
+					Self::get_opt_instruction_text(decoder, addr, version, entry.as_deref())?;
+
+				Some((decoder.op().size(), builder.into()))
+			}
+			Err(e) if data.len() >= INVALID_LEN => {
+				let builder = TextBuilder::with_invalid(e.byte()?);
+
+				Some((INVALID_LEN, builder.into()))
+			}
+			Err(_) => None,
+		}
 	}
 
 	fn instruction_llil(
@@ -243,10 +341,13 @@ impl BaseArchitecture for Architecture {
 	}
 
 	fn registers_all(&self) -> Vec<Self::Register> {
-		vec![
+		let mut list = vec![
 			Register::Stack,  // lua stack pointer
 			Register::Return, // lua return pointer
-		]
+		];
+
+		list.extend((0..=u8::MAX).map(Register::Local));
+		list
 	}
 
 	fn registers_full_width(&self) -> Vec<Self::Register> {
@@ -286,9 +387,7 @@ impl BaseArchitecture for Architecture {
 	}
 
 	fn register_from_id(&self, id: u32) -> Option<Self::Register> {
-		u8::try_from(id)
-			.ok()
-			.and_then(|v| Register::try_from(v).ok())
+		Register::from_id(id)
 	}
 
 	fn flag_from_id(&self, _: u32) -> Option<Self::Flag> {
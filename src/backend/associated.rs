@@ -5,7 +5,6 @@ use binaryninja::{
 	binaryninjacore_sys::BNImplicitRegisterExtend,
 	llil::Register as LRegister,
 };
-use num_enum::TryFromPrimitive;
 
 pub struct RegisterInfo;
 
@@ -29,20 +28,38 @@ impl IRegisterInfo for RegisterInfo {
 	}
 }
 
-#[repr(u8)]
-#[derive(TryFromPrimitive, Clone, Copy)]
+// Luau registers follow the two pointers, so `rN` has the id `N + 2`
+#[derive(Clone, Copy)]
 pub enum Register {
 	Stack,
 	Return,
+	Local(u8),
+}
+
+impl Register {
+	const LOCAL_BASE: u32 = 2;
+
+	pub fn from_id(id: u32) -> Option<Self> {
+		match id {
+			0 => Some(Self::Stack),
+			1 => Some(Self::Return),
+			id => {
+				let local = id.checked_sub(Self::LOCAL_BASE)?.try_into().ok()?;
+
+				Some(Self::Local(local))
+			}
+		}
+	}
 }
 
 impl IRegister for Register {
 	type InfoType = RegisterInfo;
 
-	fn name(&self) -> Cow<str> {
+	fn name(&self) -> Cow<'_, str> {
 		match self {
 			Register::Stack => "stack_pointer".into(),
 			Register::Return => "return_pointer".into(),
+			Register::Local(register) => format!("r{register}").into(),
 		}
 	}
 
@@ -51,7 +68,11 @@ impl IRegister for Register {
 	}
 
 	fn id(&self) -> u32 {
-		*self as u32
+		match *self {
+			Register::Stack => 0,
+			Register::Return => 1,
+			Register::Local(register) => Self::LOCAL_BASE + u32::from(register),
+		}
 	}
 }
 
@@ -1,3 +1,3 @@
 pub mod architecture;
-mod associated;
+pub mod associated;
 mod text_builder;
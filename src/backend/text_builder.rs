@@ -1,8 +1,14 @@
 use binaryninja::string::BnString;
 
 use crate::{
-	decoder::{builtin::BuiltIn, import::Import, inst::Inst, opcode::Opcode},
-	file::data::{Function, Module, Range, Value},
+	decoder::{
+		builtin::BuiltIn,
+		import::Import,
+		inst::Inst,
+		opcode::Opcode,
+		operand::{count_of, hash_size_of, CaptureKind},
+	},
+	file::data::{Function, Module, Str, Value},
 };
 
 type TextToken = binaryninja::disassembly::InstructionTextToken;
@@ -32,12 +38,14 @@ fn new_padding_for(name: &str) -> String {
 	" ".repeat(MAX_PADDING.saturating_sub(len).max(1))
 }
 
+// `base` is where the module is mapped, for turning its file offsets into addresses
 pub struct TextBuilder {
 	buffer: Vec<TextToken>,
+	base: u64,
 }
 
 impl TextBuilder {
-	pub fn with_mnemonic(opcode: Opcode) -> Self {
+	pub fn with_mnemonic(opcode: Opcode, base: u64) -> Self {
 		let name = opcode.mnemonic();
 		let padding = new_padding_for(name);
 
@@ -46,9 +54,26 @@ impl TextBuilder {
 				TextToken::new(BnString::new(name), TextContent::Instruction),
 				TextToken::new(BnString::new(padding), TextContent::Text),
 			],
+			base,
 		}
 	}
 
+	pub fn with_invalid(byte: u8) -> Self {
+		let name = ".invalid";
+		let padding = new_padding_for(name);
+		let mut builder = Self {
+			buffer: vec![
+				TextToken::new(BnString::new(name), TextContent::Instruction),
+				TextToken::new(BnString::new(padding), TextContent::Text),
+				TextToken::new(bn_format!("{byte:#04x}"), TextContent::Integer(byte.into())),
+			],
+			base: 0,
+		};
+
+		builder.add_separator();
+		builder
+	}
+
 	pub fn add_separator(&mut self) {
 		self.buffer.push(TextToken::new(
 			BnString::new(", "),
@@ -67,17 +92,91 @@ impl TextBuilder {
 		self.add_separator();
 	}
 
-	pub fn add_register(&mut self, register: u8) {
+	pub fn add_skip(&mut self, skip: i32, target: u64) {
+		let token = TextToken::new(
+			bn_format!("skip={skip}"),
+			TextContent::PossibleAddress(target),
+		);
+
+		self.buffer.push(token);
+		self.add_separator();
+	}
+
+	fn add_label(&mut self, label: &str) {
+		let token = TextToken::new(bn_format!("{label}="), TextContent::Text);
+
+		self.buffer.push(token);
+	}
+
+	pub fn add_size(&mut self, label: &str, value: i32) {
+		let token = TextToken::new(bn_format!("{value}"), TextContent::Integer(value as u64));
+
+		self.add_label(label);
+		self.buffer.push(token);
+		self.add_separator();
+	}
+
+	pub fn add_count(&mut self, label: &str, raw: i32) {
+		match u8::try_from(raw).map(count_of) {
+			Ok(Some(count)) => self.add_size(label, count.into()),
+			Ok(None) => {
+				self.add_label(label);
+				self.buffer
+					.push(TextToken::new(BnString::new("multret"), TextContent::Text));
+				self.add_separator();
+			}
+			Err(_) => self.add_integer(raw),
+		}
+	}
+
+	pub fn add_hash_size(&mut self, raw: i32) {
+		match hash_size_of(raw) {
+			Some(size) => self.add_size("hash", size),
+			None => self.add_integer(raw),
+		}
+	}
+
+	// The kind reads as a prefix of the captured register, as in `capture ref r4`
+	pub fn add_capture_kind(&mut self, raw: i32) {
+		match CaptureKind::from_raw(raw) {
+			Some(kind) => {
+				let token = TextToken::new(bn_format!("{} ", kind.name()), TextContent::Text);
+
+				self.buffer.push(token);
+			}
+			None => self.add_integer(raw),
+		}
+	}
+
+	// Negated comparisons read as `jump_if_string not r0, "abc", 0x40`
+	pub fn add_not(&mut self, is_not: bool) {
+		if is_not {
+			self.buffer
+				.push(TextToken::new(BnString::new("not "), TextContent::Text));
+		}
+	}
+
+	fn add_name(&mut self, name: Option<&str>) {
+		if let Some(name) = name {
+			let token = TextToken::new(bn_format!(" ({name})"), TextContent::Text);
+
+			self.buffer.push(token);
+		}
+	}
+
+	pub fn add_register(&mut self, register: u8, name: Option<&str>) {
 		let token = TextToken::new(bn_format!("r{register}"), TextContent::Register);
 
 		self.buffer.push(token);
+		self.add_name(name);
 		self.add_separator();
 	}
 
-	pub fn add_upvalue(&mut self, upvalue: u8) {
+	pub fn add_upvalue(&mut self, upvalue: u8, name: Option<&str>) {
 		let token = TextToken::new(bn_format!("u{upvalue}"), TextContent::Register);
 
 		self.buffer.push(token);
+		self.add_name(name);
 		self.add_separator();
 	}
 
@@ -94,6 +193,19 @@ impl TextBuilder {
 		self.add_named_integer(name);
 	}
 
+	// Anything `add` pushed before failing is dropped, so a bad index shows up as is
+	pub fn add_or_integer<A>(&mut self, raw: i32, add: A)
+	where
+		A: FnOnce(&mut Self) -> Option<()>,
+	{
+		let len = self.buffer.len();
+
+		if add(self).is_none() {
+			self.buffer.truncate(len);
+			self.add_integer(raw);
+		}
+	}
+
 	pub fn add_integer(&mut self, value: i32) {
 		let name = format!("{value}_i32");
 
@@ -107,7 +219,18 @@ impl TextBuilder {
 		self.add_separator();
 	}
 
-	fn add_string(&mut self, index: usize, str_list: &[Range]) -> Option<()> {
+	fn add_vector(&mut self, value: &[f32; 4]) {
+		let [x, y, z, w] = value;
+		let token = TextToken::new(
+			bn_format!("vector({x}, {y}, {z}, {w})"),
+			TextContent::FloatingPoint,
+		);
+
+		self.buffer.push(token);
+		self.add_separator();
+	}
+
+	fn add_string(&mut self, index: usize, str_list: &[Str]) -> Option<()> {
 		if index == 0 {
 			self.add_named_integer("no_string");
 
@@ -115,13 +238,13 @@ impl TextBuilder {
 		}
 
 		let adjusted = index - 1;
-		let address = str_list.get(adjusted)?.start;
+		let address = self.base + str_list.get(adjusted)?.range.start as u64;
 
 		let list = surrounded!(
 			"[",
 			TextToken::new(
 				bn_format!("str_{adjusted}"),
-				TextContent::PossibleAddress(address as u64),
+				TextContent::PossibleAddress(address),
 			),
 			"]"
 		);
@@ -132,6 +255,38 @@ impl TextBuilder {
 		Some(())
 	}
 
+	fn add_table(&mut self, key_list: &[usize], func: &Function, parent: &Module) {
+		let list = &func.constant_list().data;
+
+		self.buffer.push(TextToken::new(
+			BnString::new("{"),
+			TextContent::BeginMemoryOperand,
+		));
+
+		for (i, key) in key_list.iter().enumerate() {
+			let name = match list.get(*key) {
+				Some(Value::String(index)) => parent
+					.string_at(*index)
+					.map_or_else(|| format!("k{key}"), |v| v.to_string_lossy().into()),
+				Some(Value::Number(n)) => format!("[{n}]"),
+				_ => format!("k{key}"),
+			};
+
+			if i != 0 {
+				self.add_separator();
+			}
+
+			self.buffer
+				.push(TextToken::new(BnString::new(name), TextContent::Text));
+		}
+
+		self.buffer.push(TextToken::new(
+			BnString::new("}"),
+			TextContent::EndMemoryOperand,
+		));
+		self.add_separator();
+	}
+
 	pub fn add_constant(&mut self, value: &Value, func: &Function, parent: &Module) -> Option<()> {
 		match value {
 			Value::Nil => self.add_named_integer("nil"),
@@ -145,28 +300,32 @@ impl TextBuilder {
 				self.add_function(*index, global)?;
 			}
 			Value::Import(data) => self.add_import(*data, func, parent)?,
-			Value::Table => self.add_named_integer("any_table"),
+			Value::Table(key_list) => self.add_table(key_list, func, parent),
+			Value::Vector(data) => self.add_vector(data),
 		};
 
 		Some(())
 	}
 
-	pub fn add_built_in(&mut self, index: u8) -> Option<()> {
-		let name = BuiltIn::try_from(index).ok()?.name();
+	// Builtins this table does not know yet are still shown by their id
+	pub fn add_built_in(&mut self, index: u8, version: u8) {
+		let name = match BuiltIn::decode(index, version) {
+			Some(built_in) => BnString::new(built_in.name()),
+			None => bn_format!("builtin_#{index}"),
+		};
+
 		let list = surrounded!(
 			"\"",
-			TextToken::new(BnString::new(name), TextContent::FloatingPoint,),
+			TextToken::new(name, TextContent::FloatingPoint,),
 			"\""
 		);
 
 		self.buffer.extend(list);
 		self.add_separator();
-
-		Some(())
 	}
 
 	pub fn add_function(&mut self, index: usize, global: &[Function]) -> Option<()> {
-		let target = global.get(index)?.code().start as u64;
+		let target = self.base + global.get(index)?.code().start as u64;
 
 		let list = surrounded!(
 			"[",
@@ -198,7 +357,7 @@ impl TextBuilder {
 
 impl From<TextBuilder> for Vec<TextToken> {
 	fn from(mut builder: TextBuilder) -> Self {
-		builder.buffer.pop().unwrap();
+		builder.buffer.pop();
 
 		builder.buffer
 	}
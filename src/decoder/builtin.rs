@@ -79,9 +79,86 @@ pub enum BuiltIn {
 
 	GetMetatable,
 	SetMetatable,
+
+	Tonumber,
+	Tostring,
+
+	Byteswap,
+
+	ReadI8,
+	ReadU8,
+	WriteU8,
+	ReadI16,
+	ReadU16,
+	WriteU16,
+	ReadI32,
+	ReadU32,
+	WriteU32,
+	ReadF32,
+	WriteF32,
+	ReadF64,
+	WriteF64,
+
+	Magnitude,
+	Normalize,
+	Cross,
+	Dot,
+	VectorFloor,
+	VectorCeil,
+	VectorAbs,
+	VectorSign,
+	VectorClamp,
+	VectorMin,
+	VectorMax,
+
+	Lerp,
 }
 
 impl BuiltIn {
+	// The bytecode version compilers were targeting when they started emitting each
+	// builtin. Builtins never bumped the version themselves, so this follows the
+	// compiler's version target at the time rather than anything in the format
+	const fn min_version(self) -> u8 {
+		match self {
+			// The buffer library, added once compilers targeted version 4
+			Self::ReadI8
+			| Self::ReadU8
+			| Self::WriteU8
+			| Self::ReadI16
+			| Self::ReadU16
+			| Self::WriteU16
+			| Self::ReadI32
+			| Self::ReadU32
+			| Self::WriteU32
+			| Self::ReadF32
+			| Self::WriteF32
+			| Self::ReadF64
+			| Self::WriteF64 => 4,
+			// The vector library and `math.lerp`, added once compilers targeted version 6
+			Self::Magnitude
+			| Self::Normalize
+			| Self::Cross
+			| Self::Dot
+			| Self::VectorFloor
+			| Self::VectorCeil
+			| Self::VectorAbs
+			| Self::VectorSign
+			| Self::VectorClamp
+			| Self::VectorMin
+			| Self::VectorMax
+			| Self::Lerp => 6,
+			// Everything up to `bit32.byteswap`, including `tonumber` and `tostring`,
+			// is emitted by version 3 compilers
+			_ => 3,
+		}
+	}
+
+	pub fn decode(id: u8, version: u8) -> Option<Self> {
+		Self::try_from(id)
+			.ok()
+			.filter(|v| version >= v.min_version())
+	}
+
 	pub fn name(self) -> &'static str {
 		match self {
 			Self::Assert => "assert",
@@ -145,6 +222,83 @@ impl BuiltIn {
 			Self::ExtractK => "bit32.extract",
 			Self::GetMetatable => "getmetatable",
 			Self::SetMetatable => "setmetatable",
+			Self::Tonumber => "tonumber",
+			Self::Tostring => "tostring",
+			Self::Byteswap => "bit32.byteswap",
+			Self::ReadI8 => "buffer.readi8",
+			Self::ReadU8 => "buffer.readu8",
+			Self::WriteU8 => "buffer.writeu8",
+			Self::ReadI16 => "buffer.readi16",
+			Self::ReadU16 => "buffer.readu16",
+			Self::WriteU16 => "buffer.writeu16",
+			Self::ReadI32 => "buffer.readi32",
+			Self::ReadU32 => "buffer.readu32",
+			Self::WriteU32 => "buffer.writeu32",
+			Self::ReadF32 => "buffer.readf32",
+			Self::WriteF32 => "buffer.writef32",
+			Self::ReadF64 => "buffer.readf64",
+			Self::WriteF64 => "buffer.writef64",
+			Self::Magnitude => "vector.magnitude",
+			Self::Normalize => "vector.normalize",
+			Self::Cross => "vector.cross",
+			Self::Dot => "vector.dot",
+			Self::VectorFloor => "vector.floor",
+			Self::VectorCeil => "vector.ceil",
+			Self::VectorAbs => "vector.abs",
+			Self::VectorSign => "vector.sign",
+			Self::VectorClamp => "vector.clamp",
+			Self::VectorMin => "vector.min",
+			Self::VectorMax => "vector.max",
+			Self::Lerp => "math.lerp",
 		}
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::BuiltIn;
+
+	#[test]
+	fn decode_by_version() {
+		assert!(matches!(BuiltIn::decode(62, 3), Some(BuiltIn::Tonumber)));
+		assert!(matches!(BuiltIn::decode(64, 3), Some(BuiltIn::Byteswap)));
+		assert!(BuiltIn::decode(65, 3).is_none());
+		assert!(BuiltIn::decode(89, 5).is_none());
+		assert!(matches!(BuiltIn::decode(89, 6), Some(BuiltIn::Lerp)));
+		assert!(BuiltIn::decode(0, 6).is_none());
+		assert!(BuiltIn::decode(90, 6).is_none());
+	}
+
+	// Every builtin appears at its first version and not a version before
+	#[test]
+	fn decode_each_boundary() {
+		let expected = |id: u8| match id {
+			1..=64 => 3,
+			65..=77 => 4,
+			78..=89 => 6,
+			_ => unreachable!(),
+		};
+
+		for id in 1..=89 {
+			let version = expected(id);
+			let name = BuiltIn::decode(id, version).map(BuiltIn::name);
+
+			assert!(name.is_some(), "builtin {id} missing at version {version}");
+			assert!(BuiltIn::decode(id, version - 1).is_none());
+		}
+
+		assert_eq!(
+			BuiltIn::decode(65, 4).map(BuiltIn::name),
+			Some("buffer.readi8")
+		);
+		assert_eq!(
+			BuiltIn::decode(77, 4).map(BuiltIn::name),
+			Some("buffer.writef64")
+		);
+		assert_eq!(
+			BuiltIn::decode(78, 6).map(BuiltIn::name),
+			Some("vector.magnitude")
+		);
+		assert!(BuiltIn::decode(78, 5).is_none());
+	}
+}
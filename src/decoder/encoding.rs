@@ -0,0 +1,28 @@
+// Roblox stores every opcode multiplied by 227, and 203 is its inverse modulo 256
+const ROBLOX_INVERSE: u8 = 203;
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+	#[default]
+	Plain,
+	Roblox,
+}
+
+impl Encoding {
+	pub const LIST: [Self; 2] = [Self::Plain, Self::Roblox];
+
+	pub const fn id(self) -> u8 {
+		self as u8
+	}
+
+	pub fn from_id(id: u8) -> Option<Self> {
+		Self::LIST.into_iter().find(|v| v.id() == id)
+	}
+
+	pub const fn decode(self, byte: u8) -> u8 {
+		match self {
+			Self::Plain => byte,
+			Self::Roblox => byte.wrapping_mul(ROBLOX_INVERSE),
+		}
+	}
+}
@@ -13,9 +13,10 @@ impl Iterator for Import {
 			return None;
 		}
 
-		let value = self.data & 0x3FF;
+		// Ids are packed from the top, as in `count << 30 | id0 << 20 | id1 << 10 | id2`
+		let value = (self.data >> 20) & 0x3FF;
 
-		self.data = self.data >> 10 | (len - 1) << 30;
+		self.data = (self.data << 10) & 0x3FFF_FFFF | (len - 1) << 30;
 
 		Some(value.try_into().unwrap())
 	}
@@ -1,23 +1,58 @@
-use super::opcode::{OpName, Opcode};
+use super::{
+	encoding::Encoding,
+	opcode::{OpName, Opcode},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+	Empty,
+	Unknown(u8),
+	Truncated(u8),
+}
+
+impl DecodeError {
+	// The raw, still encoded, first byte of the instruction
+	pub fn byte(self) -> Option<u8> {
+		match self {
+			Self::Empty => None,
+			Self::Unknown(byte) | Self::Truncated(byte) => Some(byte),
+		}
+	}
+}
 
 #[derive(Clone, Copy)]
-pub struct Inst<'a>(&'a [u8]);
+pub struct Inst<'a> {
+	data: &'a [u8],
+	op: Opcode,
+}
 
 impl<'a> Inst<'a> {
+	pub fn decode(data: &'a [u8], encoding: Encoding, version: u8) -> Result<Self, DecodeError> {
+		let first = *data.first().ok_or(DecodeError::Empty)?;
+		let op =
+			Opcode::decode(encoding.decode(first), version).ok_or(DecodeError::Unknown(first))?;
+
+		if data.len() < op.size() {
+			Err(DecodeError::Truncated(first))
+		} else {
+			Ok(Self { data, op })
+		}
+	}
+
 	pub fn op(&self) -> Opcode {
-		Opcode::try_from(self.0[0]).unwrap()
+		self.op
 	}
 
 	pub fn a(&self) -> u8 {
-		self.0[1]
+		self.data[1]
 	}
 
 	pub fn b(&self) -> u8 {
-		self.0[2]
+		self.data[2]
 	}
 
 	pub fn c(&self) -> u8 {
-		self.0[3]
+		self.data[3]
 	}
 
 	pub fn d(&self) -> i16 {
@@ -35,20 +70,42 @@ impl<'a> Inst<'a> {
 		i32::from_le_bytes([0, a, b, c])
 	}
 
-	pub fn adjacent(&self) -> i32 {
-		let data = self.0[4..8].try_into().unwrap();
+	// Only two word opcodes have an aux word, anything after a one word opcode
+	// belongs to the next instruction
+	pub fn adjacent(&self) -> Option<i32> {
+		if self.op.size() != 8 {
+			return None;
+		}
+
+		let data = self.data.get(4..8)?.try_into().ok()?;
 
-		i32::from_le_bytes(data)
+		Some(i32::from_le_bytes(data))
 	}
 
-	pub fn with_name(&self, name: OpName) -> i32 {
-		match name {
+	pub fn with_name(&self, name: OpName) -> Option<i32> {
+		let value = match name {
 			OpName::A => self.a().into(),
 			OpName::B => self.b().into(),
 			OpName::C => self.c().into(),
 			OpName::D => self.d().into(),
 			OpName::E => self.e(),
-			OpName::X => self.adjacent(),
+			OpName::X => self.adjacent()?,
+			OpName::XA => self.adjacent()? & 0xFF,
+			OpName::XB => (self.adjacent()? >> 8) & 0xFF,
+			OpName::XK => self.adjacent()? & 0xFF_FFFF,
+			OpName::XNot => (self.adjacent()? as u32 >> 31) as i32,
+		};
+
+		Some(value)
+	}
+
+	// Fast calls jump past the call they stand in for, which sits `C` words after the
+	// next one whether or not the fast call has an aux word
+	pub fn location_target(&self, addr: u64, offset: i64) -> u64 {
+		if self.op.is_fast_call() {
+			Self::get_jump_target(addr, offset + 1)
+		} else {
+			Self::get_jump_target(addr, offset)
 		}
 	}
 
@@ -56,21 +113,32 @@ impl<'a> Inst<'a> {
 	where
 		T: Into<i64>,
 	{
-		start.wrapping_add_signed(offset.into() * 4) + 4
+		start
+			.wrapping_add_signed(offset.into().wrapping_mul(4))
+			.wrapping_add(4)
 	}
 }
 
-impl<'a> TryFrom<&'a [u8]> for Inst<'a> {
-	type Error = ();
+#[cfg(test)]
+mod test {
+	use super::Inst;
+	use crate::decoder::{encoding::Encoding, opcode::OpName};
 
-	fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
-		let first = *data.first().ok_or(())?;
-		let len = Opcode::try_from(first).map_err(drop)?.len();
+	#[test]
+	fn adjacent_only_on_two_words() {
+		// return r0, 1 followed by the next instruction
+		let inst = Inst::decode(&[22, 0, 1, 0, 12, 0, 1, 0], Encoding::Plain, 6).unwrap();
 
-		if data.len() < len {
-			Err(())
-		} else {
-			Ok(Self(data))
-		}
+		assert_eq!(inst.adjacent(), None);
+		assert_eq!(inst.with_name(OpName::X), None);
+
+		let inst = Inst::decode(&[22, 0, 1, 0], Encoding::Plain, 6).unwrap();
+
+		assert_eq!(inst.adjacent(), None);
+
+		// get_import r0, K1, aux
+		let inst = Inst::decode(&[12, 0, 1, 0, 0, 0, 0, 0x40], Encoding::Plain, 6).unwrap();
+
+		assert_eq!(inst.adjacent(), Some(0x4000_0000));
 	}
 }
@@ -1,4 +1,6 @@
 pub mod builtin;
+pub mod encoding;
 pub mod import;
 pub mod inst;
 pub mod opcode;
+pub mod operand;
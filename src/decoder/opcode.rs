@@ -1,5 +1,3 @@
-use num_enum::TryFromPrimitive;
-
 #[derive(Clone, Copy)]
 pub enum OpName {
 	A,
@@ -8,11 +6,19 @@ pub enum OpName {
 	D,
 	E,
 	X,
+	// The low two bytes of the aux word, used separately by some opcodes
+	XA,
+	XB,
+	// The low 24 bits and the top bit of the aux word of the constant comparisons
+	XK,
+	XNot,
 }
 
 #[derive(Clone, Copy)]
 pub enum OpType {
 	Location,
+	// A fast call skip, which jumps past the call it stands in for
+	Skip,
 	Register,
 	UpValue,
 	Boolean,
@@ -21,12 +27,24 @@ pub enum OpType {
 	Function,
 	Import,
 	BuiltIn,
+	// Encoded as the count plus one, with zero meaning everything up to the stack top
+	Count(&'static str),
+	Size(&'static str),
+	// Encoded as the log2 of the size plus one
+	HashSize,
+	CaptureKind,
+	// A register or an upvalue, depending on the capture kind
+	CaptureIndex,
+	// Flips the sense of a comparison
+	Not,
 }
 
-#[repr(u8)]
-#[derive(TryFromPrimitive, Clone, Copy)]
+// The newest bytecode version we know the opcode layout of
+pub const LATEST_VERSION: u8 = 6;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Opcode {
-	Nop = 0,
+	Nop,
 	Break,
 
 	LoadNil,
@@ -143,10 +161,118 @@ pub enum Opcode {
 	JumpIfBoolean,
 	JumpIfNumber,
 	JumpIfString,
+
+	FloorDiv,
+	FloorDivConstant,
+
+	ReverseSubConstant,
+	ReverseDivConstant,
+
+	FastCall3,
+
+	NativeCall,
 }
 
 impl Opcode {
-	pub const fn len(self) -> usize {
+	// Some slots were retired and later reused, so the meaning of a byte depends
+	// on the bytecode version of the module it comes from
+	pub const fn decode(byte: u8, version: u8) -> Option<Self> {
+		let op = match byte {
+			0 => Self::Nop,
+			1 => Self::Break,
+			2 => Self::LoadNil,
+			3 => Self::LoadBoolean,
+			4 => Self::LoadInteger,
+			5 => Self::LoadConstant,
+			6 => Self::Move,
+			7 => Self::GetGlobal,
+			8 => Self::SetGlobal,
+			9 => Self::GetUpValue,
+			10 => Self::SetUpValue,
+			11 => Self::CloseUpValues,
+			12 => Self::GetImport,
+			13 => Self::GetTable,
+			14 => Self::SetTable,
+			15 => Self::GetTableKey,
+			16 => Self::SetTableKey,
+			17 => Self::GetTableIndex,
+			18 => Self::SetTableIndex,
+			19 => Self::NewClosure,
+			20 => Self::NameCall,
+			21 => Self::Call,
+			22 => Self::Return,
+			23 => Self::Jump,
+			24 => Self::JumpSafe,
+			25 => Self::JumpIfTruthy,
+			26 => Self::JumpIfFalsy,
+			27 => Self::JumpIfEqual,
+			28 => Self::JumpIfLessEqual,
+			29 => Self::JumpIfLessThan,
+			30 => Self::JumpIfNotEqual,
+			31 => Self::JumpIfMoreThan,
+			32 => Self::JumpIfMoreEqual,
+			33 => Self::Add,
+			34 => Self::Sub,
+			35 => Self::Mul,
+			36 => Self::Div,
+			37 => Self::Mod,
+			38 => Self::Pow,
+			39 => Self::AddConstant,
+			40 => Self::SubConstant,
+			41 => Self::MulConstant,
+			42 => Self::DivConstant,
+			43 => Self::ModConstant,
+			44 => Self::PowConstant,
+			45 => Self::And,
+			46 => Self::Or,
+			47 => Self::AndConstant,
+			48 => Self::OrConstant,
+			49 => Self::Concat,
+			50 => Self::Not,
+			51 => Self::Minus,
+			52 => Self::Length,
+			53 => Self::NewTable,
+			54 => Self::DupTable,
+			55 => Self::SetList,
+			56 => Self::ForNumericPrep,
+			57 => Self::ForNumericLoop,
+			58 => Self::ForGenericLoop,
+			59 => Self::ForGenericPrepINext,
+			60 if version >= 6 => Self::FastCall3,
+			60 if version < 3 => Self::ForGenericLoopINext,
+			61 => Self::ForGenericPrepNext,
+			62 if version >= 3 => Self::NativeCall,
+			62 => Self::ForGenericLoopNext,
+			63 => Self::GetVariadic,
+			64 => Self::DupClosure,
+			65 => Self::PrepVariadic,
+			66 => Self::LoadConstantEx,
+			67 => Self::JumpEx,
+			68 => Self::FastCall,
+			69 => Self::Coverage,
+			70 => Self::Capture,
+			71 if version >= 5 => Self::ReverseSubConstant,
+			71 if version < 3 => Self::JumpIfConstant,
+			72 if version >= 5 => Self::ReverseDivConstant,
+			72 if version < 3 => Self::JumpIfNotConstant,
+			73 => Self::FastCall1,
+			74 => Self::FastCall2,
+			75 => Self::FastCall2K,
+			76 => Self::ForGenericPrep,
+			77 => Self::JumpIfNil,
+			78 => Self::JumpIfBoolean,
+			79 => Self::JumpIfNumber,
+			80 => Self::JumpIfString,
+			81 if version >= 4 => Self::FloorDiv,
+			82 if version >= 4 => Self::FloorDivConstant,
+			_ => return None,
+		};
+
+		Some(op)
+	}
+
+	// Size in bytes, including the aux word
+	pub const fn size(self) -> usize {
 		match self {
 			Self::GetGlobal
 			| Self::SetGlobal
@@ -171,11 +297,20 @@ impl Opcode {
 			| Self::JumpIfNil
 			| Self::JumpIfBoolean
 			| Self::JumpIfNumber
-			| Self::JumpIfString => 8,
+			| Self::JumpIfString
+			| Self::FastCall3 => 8,
 			_ => 4,
 		}
 	}
 
+	// Fast calls stand in for the call after them, which they skip on success
+	pub const fn is_fast_call(self) -> bool {
+		matches!(
+			self,
+			Self::FastCall | Self::FastCall1 | Self::FastCall2 | Self::FastCall2K | Self::FastCall3
+		)
+	}
+
 	pub const fn mnemonic(self) -> &'static str {
 		match self {
 			Self::Nop => "nop",
@@ -259,12 +394,18 @@ impl Opcode {
 			Self::JumpIfBoolean => "jump_if_boolean",
 			Self::JumpIfNumber => "jump_if_number",
 			Self::JumpIfString => "jump_if_string",
+			Self::FloorDiv => "floor_div",
+			Self::FloorDivConstant => "floor_div_constant",
+			Self::ReverseSubConstant => "reverse_sub_constant",
+			Self::ReverseDivConstant => "reverse_div_constant",
+			Self::FastCall3 => "fast_call3",
+			Self::NativeCall => "native_call",
 		}
 	}
 
 	#[allow(clippy::match_same_arms)]
 	const fn name_list(self) -> &'static [OpName] {
-		use OpName::{A, B, C, D, E, X};
+		use OpName::{XNot, A, B, C, D, E, X, XA, XB, XK};
 
 		match self {
 			Self::Nop => &[],
@@ -337,24 +478,31 @@ impl Opcode {
 			Self::JumpEx => &[E],
 			Self::FastCall => &[A, C],
 			Self::Coverage => &[E],
-			Self::Capture => &[],
+			Self::Capture => &[A, B],
 			Self::JumpIfConstant => &[A, X, D],
 			Self::JumpIfNotConstant => &[A, X, D],
 			Self::FastCall1 => &[A, B, C],
 			Self::FastCall2 => &[A, B, X, C],
 			Self::FastCall2K => &[A, B, X, C],
 			Self::ForGenericPrep => &[A, D],
-			Self::JumpIfNil => &[A, D, X],
-			Self::JumpIfBoolean => &[A, D, X],
-			Self::JumpIfNumber => &[A, D, X],
-			Self::JumpIfString => &[A, D, X],
+			Self::JumpIfNil => &[XNot, A, D],
+			Self::JumpIfBoolean => &[XNot, A, XK, D],
+			Self::JumpIfNumber => &[XNot, A, XK, D],
+			Self::JumpIfString => &[XNot, A, XK, D],
+			Self::FloorDiv => &[A, B, C],
+			Self::FloorDivConstant => &[A, B, C],
+			Self::ReverseSubConstant => &[A, B, C],
+			Self::ReverseDivConstant => &[A, B, C],
+			Self::FastCall3 => &[A, B, XA, XB, C],
+			Self::NativeCall => &[],
 		}
 	}
 
 	#[allow(clippy::match_same_arms)]
 	const fn type_list(self) -> &'static [OpType] {
 		use OpType::{
-			Boolean, BuiltIn, Constant, Function, Import, Integer, Location, Register, UpValue,
+			Boolean, BuiltIn, CaptureIndex, CaptureKind, Constant, Count, Function, HashSize,
+			Import, Integer, Location, Not, Register, Size, Skip, UpValue,
 		};
 
 		match self {
@@ -379,8 +527,8 @@ impl Opcode {
 			Self::SetTableIndex => &[Register, Register, Integer],
 			Self::NewClosure => &[Register, Function],
 			Self::NameCall => &[Register, Register, Constant],
-			Self::Call => &[Register, Integer, Integer],
-			Self::Return => &[Register, Integer],
+			Self::Call => &[Register, Count("args"), Count("rets")],
+			Self::Return => &[Register, Count("values")],
 			Self::Jump => &[Location],
 			Self::JumpSafe => &[Location],
 			Self::JumpIfTruthy => &[Register, Location],
@@ -411,9 +559,9 @@ impl Opcode {
 			Self::Not => &[Register, Register],
 			Self::Minus => &[Register, Register],
 			Self::Length => &[Register, Register],
-			Self::NewTable => &[Register, Integer, Integer],
+			Self::NewTable => &[Register, HashSize, Size("array")],
 			Self::DupTable => &[Register, Constant],
-			Self::SetList => &[Register, Register, Integer, Integer],
+			Self::SetList => &[Register, Register, Count("count"), Size("index")],
 			Self::ForNumericPrep => &[Register, Location],
 			Self::ForNumericLoop => &[Register, Location],
 			Self::ForGenericLoop => &[Register, Integer, Location],
@@ -421,24 +569,30 @@ impl Opcode {
 			Self::ForGenericLoopINext => &[Register, Location],
 			Self::ForGenericPrepNext => &[Register, Location],
 			Self::ForGenericLoopNext => &[Register, Location],
-			Self::GetVariadic => &[Register, Integer],
+			Self::GetVariadic => &[Register, Count("count")],
 			Self::DupClosure => &[Register, Constant],
 			Self::PrepVariadic => &[Integer],
 			Self::LoadConstantEx => &[Register, Constant],
 			Self::JumpEx => &[Location],
-			Self::FastCall => &[BuiltIn, Location],
+			Self::FastCall => &[BuiltIn, Skip],
 			Self::Coverage => &[Integer],
-			Self::Capture => &[],
+			Self::Capture => &[CaptureKind, CaptureIndex],
 			Self::JumpIfConstant => &[Register, Constant, Location],
 			Self::JumpIfNotConstant => &[Register, Constant, Location],
-			Self::FastCall1 => &[BuiltIn, Register, Location],
-			Self::FastCall2 => &[BuiltIn, Register, Register, Location],
-			Self::FastCall2K => &[BuiltIn, Register, Constant, Location],
+			Self::FastCall1 => &[BuiltIn, Register, Skip],
+			Self::FastCall2 => &[BuiltIn, Register, Register, Skip],
+			Self::FastCall2K => &[BuiltIn, Register, Constant, Skip],
 			Self::ForGenericPrep => &[Register, Location],
-			Self::JumpIfNil => &[Register, Location, Integer],
-			Self::JumpIfBoolean => &[Register, Location, Integer],
-			Self::JumpIfNumber => &[Register, Location, Integer],
-			Self::JumpIfString => &[Register, Location, Integer],
+			Self::JumpIfNil => &[Not, Register, Location],
+			Self::JumpIfBoolean => &[Not, Register, Boolean, Location],
+			Self::JumpIfNumber => &[Not, Register, Constant, Location],
+			Self::JumpIfString => &[Not, Register, Constant, Location],
+			Self::FloorDiv => &[Register, Register, Register],
+			Self::FloorDivConstant => &[Register, Register, Constant],
+			Self::ReverseSubConstant => &[Register, Constant, Register],
+			Self::ReverseDivConstant => &[Register, Constant, Register],
+			Self::FastCall3 => &[BuiltIn, Register, Register, Register, Skip],
+			Self::NativeCall => &[],
 		}
 	}
 
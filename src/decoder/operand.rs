@@ -0,0 +1,155 @@
+use super::{import::Import, inst::Inst, opcode::OpType};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CaptureKind {
+	Value,
+	Reference,
+	UpValue,
+}
+
+impl CaptureKind {
+	pub const fn from_raw(raw: i32) -> Option<Self> {
+		match raw {
+			0 => Some(Self::Value),
+			1 => Some(Self::Reference),
+			2 => Some(Self::UpValue),
+			_ => None,
+		}
+	}
+
+	pub const fn name(self) -> &'static str {
+		match self {
+			Self::Value => "val",
+			Self::Reference => "ref",
+			Self::UpValue => "upval",
+		}
+	}
+}
+
+// Counts are stored plus one, so `None` stands for everything up to the stack top
+pub fn count_of(raw: u8) -> Option<u8> {
+	raw.checked_sub(1)
+}
+
+// Hash sizes are stored as their log2 plus one, with zero meaning no hash part
+pub fn hash_size_of(raw: i32) -> Option<i32> {
+	match raw {
+		0 => Some(0),
+		_ => 1_i32.checked_shl(u32::try_from(raw - 1).ok()?),
+	}
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Operand {
+	Register(u8),
+	UpValue(u8),
+	Constant(usize),
+	Proto(usize),
+	Jump(u64),
+	BuiltIn(u8),
+	Import(Box<[usize]>),
+	Integer(i32),
+	Boolean(bool),
+	Count(Option<u8>),
+	Capture(CaptureKind),
+	Not(bool),
+}
+
+impl Operand {
+	// Values too wide for their kind, such as a register in a corrupt aux word,
+	// are kept as plain integers rather than truncated
+	pub fn decode(inst: Inst, addr: u64, reference_list: &[usize]) -> Vec<Self> {
+		inst.op()
+			.iter_operands()
+			.filter_map(|(name, typ)| {
+				let raw = inst.with_name(name)?;
+				let value = match typ {
+					OpType::Register => u8::try_from(raw).ok().map(Self::Register),
+					OpType::UpValue => u8::try_from(raw).ok().map(Self::UpValue),
+					OpType::BuiltIn => u8::try_from(raw).ok().map(Self::BuiltIn),
+					OpType::Constant => Some(Self::Constant(raw as u32 as usize)),
+					OpType::Function => usize::try_from(raw)
+						.ok()
+						.and_then(|v| reference_list.get(v))
+						.map(|&v| Self::Proto(v)),
+					OpType::Location | OpType::Skip => {
+						Some(Self::Jump(inst.location_target(addr, raw.into())))
+					}
+					OpType::Count(_) => u8::try_from(raw).ok().map(|v| Self::Count(count_of(v))),
+					OpType::HashSize => hash_size_of(raw).map(Self::Integer),
+					OpType::CaptureKind => CaptureKind::from_raw(raw).map(Self::Capture),
+					OpType::CaptureIndex => {
+						let register = u8::try_from(raw).ok();
+
+						match CaptureKind::from_raw(inst.a().into()) {
+							Some(CaptureKind::UpValue) => register.map(Self::UpValue),
+							_ => register.map(Self::Register),
+						}
+					}
+					OpType::Import => Some(Self::Import(Import::from(raw as u32).collect())),
+					OpType::Boolean => Some(Self::Boolean(raw != 0)),
+					OpType::Not => Some(Self::Not(raw != 0)),
+					OpType::Integer | OpType::Size(_) => None,
+				};
+
+				Some(value.unwrap_or(Self::Integer(raw)))
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::Operand;
+	use crate::decoder::{encoding::Encoding, inst::Inst};
+
+	fn decode(data: &[u8]) -> Vec<Operand> {
+		let inst = Inst::decode(data, Encoding::Plain, 6).unwrap();
+
+		Operand::decode(inst, 0x100, &[])
+	}
+
+	#[test]
+	fn decode_import() {
+		// get_import r0, K0, Players.PlayerAdded from constants 1 and 2
+		let list = decode(&[12, 0, 0, 0, 0, 0x08, 0x10, 0x80]);
+
+		assert_eq!(
+			list,
+			[
+				Operand::Register(0),
+				Operand::Constant(0),
+				Operand::Import([1, 2].into()),
+			]
+		);
+	}
+
+	#[test]
+	fn decode_constant_comparison() {
+		// jump_if_number not r1, K3, +2
+		let list = decode(&[79, 1, 2, 0, 3, 0, 0, 0x80]);
+
+		assert_eq!(
+			list,
+			[
+				Operand::Not(true),
+				Operand::Register(1),
+				Operand::Constant(3),
+				Operand::Jump(0x10C),
+			]
+		);
+
+		// jump_if_boolean r0, true, -1
+		let list = decode(&[78, 0, 0xFF, 0xFF, 1, 0, 0, 0]);
+
+		assert_eq!(
+			list,
+			[
+				Operand::Not(false),
+				Operand::Register(0),
+				Operand::Boolean(true),
+				Operand::Jump(0x100),
+			]
+		);
+	}
+}
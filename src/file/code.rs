@@ -0,0 +1,48 @@
+use crate::decoder::{inst::Inst, opcode::Opcode, operand::Operand};
+
+use super::data::{Format, Function};
+
+pub struct CodeIter<'a> {
+	code: &'a [u8],
+	addr: u64,
+	format: Format,
+	func: &'a Function,
+}
+
+impl<'a> CodeIter<'a> {
+	pub fn new(data: &'a [u8], format: Format, func: &'a Function) -> Self {
+		let code = data.get(func.code()).unwrap_or_default();
+
+		Self {
+			code,
+			addr: func.code().start as u64,
+			format,
+			func,
+		}
+	}
+}
+
+// Undecodable words are skipped, matching how the architecture steps over them
+impl<'a> Iterator for CodeIter<'a> {
+	type Item = (u64, Opcode, Vec<Operand>);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		while self.code.len() >= 4 {
+			let addr = self.addr;
+			let result = Inst::decode(self.code, self.format.encoding, self.format.version);
+			let len = result.map_or(4, |inst| inst.op().size());
+
+			self.code = &self.code[len..];
+			self.addr += len as u64;
+
+			if let Ok(inst) = result {
+				let reference_list = &self.func.reference_list().data;
+				let operand_list = Operand::decode(inst, addr, reference_list);
+
+				return Some((addr, inst.op(), operand_list));
+			}
+		}
+
+		None
+	}
+}
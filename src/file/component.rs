@@ -0,0 +1,131 @@
+use std::{
+	ffi::{c_char, CStr, CString},
+	rc::Rc,
+};
+
+use binaryninja::{
+	binaryninjacore_sys::{
+		BNBinaryView, BNFreeFunctionList, BNFreeString, BNFunction,
+		BNGetAnalysisFunctionsForAddress,
+	},
+	binaryview::BinaryView,
+};
+
+use super::data::Module;
+
+#[repr(C)]
+struct BNComponent {
+	_private: [u8; 0],
+}
+
+// The bindings have no wrapper for components, so the core calls are declared here
+extern "C" {
+	fn BNCreateComponentWithName(view: *mut BNBinaryView, name: *const c_char) -> *mut BNComponent;
+	fn BNCreateComponentWithParentAndName(
+		view: *mut BNBinaryView,
+		parent: *const c_char,
+		name: *const c_char,
+	) -> *mut BNComponent;
+	fn BNComponentGetGuid(component: *mut BNComponent) -> *mut c_char;
+	fn BNComponentAddFunctionReference(component: *mut BNComponent, func: *mut BNFunction) -> bool;
+	fn BNFreeComponent(component: *mut BNComponent);
+}
+
+struct Component {
+	handle: *mut BNComponent,
+}
+
+impl Component {
+	fn new(view: &BinaryView, parent: Option<&Component>, name: &str) -> Option<Self> {
+		let name = CString::new(name).ok()?;
+
+		let handle = match parent {
+			Some(parent) => {
+				let guid = parent.guid()?;
+
+				unsafe {
+					BNCreateComponentWithParentAndName(view.handle, guid.as_ptr(), name.as_ptr())
+				}
+			}
+			None => unsafe { BNCreateComponentWithName(view.handle, name.as_ptr()) },
+		};
+
+		(!handle.is_null()).then_some(Self { handle })
+	}
+
+	fn guid(&self) -> Option<CString> {
+		unsafe {
+			let raw = BNComponentGetGuid(self.handle);
+
+			if raw.is_null() {
+				return None;
+			}
+
+			let guid = CStr::from_ptr(raw).to_owned();
+
+			BNFreeString(raw);
+
+			Some(guid)
+		}
+	}
+
+	fn add_function_at(&self, view: &BinaryView, addr: u64) {
+		unsafe {
+			let mut count = 0;
+			let list = BNGetAnalysisFunctionsForAddress(view.handle, addr, &mut count);
+
+			if list.is_null() {
+				return;
+			}
+
+			for &func in std::slice::from_raw_parts(list, count) {
+				BNComponentAddFunctionReference(self.handle, func);
+			}
+
+			BNFreeFunctionList(list, count);
+		}
+	}
+}
+
+impl Drop for Component {
+	fn drop(&mut self) {
+		unsafe { BNFreeComponent(self.handle) };
+	}
+}
+
+// Every function with closures gets a folder holding itself and the folders or
+// functions of its children, so the function list mirrors the proto tree
+pub fn add_folders(view: &BinaryView, module: &Module, base: u64) {
+	let function_list = &module.function_list().data;
+	let tree = module.proto_tree();
+	let mut work_list: Vec<(Option<Rc<Component>>, usize)> =
+		tree.root_list().map(|v| (None, v)).collect();
+
+	while let Some((parent, index)) = work_list.pop() {
+		let Some(func) = function_list.get(index) else {
+			continue;
+		};
+
+		let addr = base + func.code().start as u64;
+		let child_list = tree.children_of(index);
+
+		if child_list.is_empty() {
+			if let Some(parent) = &parent {
+				parent.add_function_at(view, addr);
+			}
+
+			continue;
+		}
+
+		let name = module.function_name(index);
+		let Some(component) = Component::new(view, parent.as_deref(), &name) else {
+			continue;
+		};
+
+		component.add_function_at(view, addr);
+
+		let component = Rc::new(component);
+
+		work_list.extend(child_list.iter().map(|&v| (Some(component.clone()), v)));
+	}
+}
@@ -1,14 +1,34 @@
-use std::cmp::Ordering;
+use std::{borrow::Cow, cmp::Ordering, collections::HashMap};
+
+use crate::decoder::encoding::Encoding;
+
+use super::{code::CodeIter, tree::ProtoTree};
 
 pub type Range = std::ops::Range<usize>;
 
+// `offset_list` holds where each entry starts, for pointing diagnostics at it
 #[derive(Default)]
 pub struct List<T> {
 	pub data: Box<[T]>,
+	pub offset_list: Box<[usize]>,
+	pub range: Range,
+}
+
+#[derive(Default)]
+pub struct Str {
 	pub range: Range,
+	pub data: Box<[u8]>,
+}
+
+impl Str {
+	pub fn to_string_lossy(&self) -> Cow<'_, str> {
+		String::from_utf8_lossy(&self.data)
+	}
 }
 
+#[derive(Default)]
 pub enum Value {
+	#[default]
 	Nil,
 	False,
 	True,
@@ -16,15 +36,120 @@ pub enum Value {
 	String(usize),
 	Closure(usize),
 	Import(u32),
+	Table(Box<[usize]>),
+	Vector([f32; 4]),
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Format {
+	pub version: u8,
+	pub type_version: u8,
+	pub encoding: Encoding,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TypeKind {
+	Nil,
+	Boolean,
+	Number,
+	String,
 	Table,
+	Function,
+	Thread,
+	UserData,
+	Vector,
+	Buffer,
+	Any,
+	Tagged(u8),
+	Unknown(u8),
+}
+
+impl TypeKind {
+	pub const fn name(self) -> &'static str {
+		match self {
+			Self::Nil => "nil",
+			Self::Boolean => "boolean",
+			Self::Number => "number",
+			Self::String => "string",
+			Self::Table => "table",
+			Self::Function => "function",
+			Self::Thread => "thread",
+			Self::UserData | Self::Tagged(_) => "userdata",
+			Self::Vector => "vector",
+			Self::Buffer => "buffer",
+			Self::Any | Self::Unknown(_) => "any",
+		}
+	}
+}
+
+#[derive(Clone, Copy)]
+pub struct TypeTag {
+	pub kind: TypeKind,
+	pub is_optional: bool,
+}
+
+pub struct TypedLocal {
+	pub tag: TypeTag,
+	pub register: u8,
+	pub start_pc: usize,
+	pub end_pc: usize,
+}
+
+#[derive(Default)]
+pub struct TypeInfo {
+	pub param_list: Box<[TypeTag]>,
+	pub upvalue_list: Box<[TypeTag]>,
+	pub local_list: Box<[TypedLocal]>,
+}
+
+#[derive(Default)]
+pub struct UserDataType {
+	pub index: u8,
+	pub name: usize,
+}
+
+#[derive(Default)]
+pub struct MetaData {
+	pub max_stack_size: u8,
+	pub num_param: u8,
+	pub num_upval: u8,
+	pub is_vararg: bool,
+	pub flags: u8,
+	pub type_range: Range,
+	pub type_info: Option<TypeInfo>,
+	pub line_defined: usize,
+}
+
+pub struct LineInfo {
+	pub gap_log2: u8,
+	pub offset_list: Box<[u8]>,
+	pub line_list: Box<[i32]>,
 }
 
-impl Default for Value {
-	fn default() -> Self {
-		Self::Nil
+impl LineInfo {
+	pub fn line_at(&self, pc: usize) -> Option<i32> {
+		let offset = *self.offset_list.get(pc)?;
+		let interval = pc.checked_shr(self.gap_log2.into())?;
+		let line = *self.line_list.get(interval)?;
+
+		Some(line.wrapping_add(offset.into()))
 	}
 }
 
+pub struct Local {
+	pub name: usize,
+	pub start_pc: usize,
+	pub end_pc: usize,
+	pub register: u8,
+}
+
+#[derive(Default)]
+pub struct DebugInfo {
+	pub line_info: Option<LineInfo>,
+	pub local_list: Box<[Local]>,
+	pub upvalue_list: Box<[usize]>,
+}
+
 #[derive(Default)]
 pub struct Function {
 	position: Range,
@@ -32,6 +157,8 @@ pub struct Function {
 	code: Range,
 	constant_list: List<Value>,
 	reference_list: List<usize>,
+	meta_data: MetaData,
+	debug_info: DebugInfo,
 }
 
 impl Function {
@@ -41,6 +168,8 @@ impl Function {
 		code: Range,
 		constant_list: List<Value>,
 		reference_list: List<usize>,
+		meta_data: MetaData,
+		debug_info: DebugInfo,
 	) -> Self {
 		Self {
 			position,
@@ -48,6 +177,8 @@ impl Function {
 			code,
 			constant_list,
 			reference_list,
+			meta_data,
+			debug_info,
 		}
 	}
 
@@ -70,13 +201,109 @@ impl Function {
 	pub fn reference_list(&self) -> &List<usize> {
 		&self.reference_list
 	}
+
+	pub fn meta_data(&self) -> &MetaData {
+		&self.meta_data
+	}
+
+	pub fn max_stack_size(&self) -> u8 {
+		self.meta_data.max_stack_size
+	}
+
+	pub fn num_param(&self) -> u8 {
+		self.meta_data.num_param
+	}
+
+	pub fn num_upval(&self) -> u8 {
+		self.meta_data.num_upval
+	}
+
+	pub fn is_vararg(&self) -> bool {
+		self.meta_data.is_vararg
+	}
+
+	pub fn line_defined(&self) -> usize {
+		self.meta_data.line_defined
+	}
+
+	pub fn type_info(&self) -> Option<&TypeInfo> {
+		self.meta_data.type_info.as_ref()
+	}
+
+	pub fn debug_info(&self) -> &DebugInfo {
+		&self.debug_info
+	}
+
+	pub fn iter_code<'a>(&'a self, data: &'a [u8], format: Format) -> CodeIter<'a> {
+		CodeIter::new(data, format, self)
+	}
+
+	pub fn pc_at(&self, addr: u64) -> Option<usize> {
+		let addr = usize::try_from(addr).ok()?;
+
+		if self.code.contains(&addr) {
+			Some((addr - self.code.start) / 4)
+		} else {
+			None
+		}
+	}
+
+	pub fn line_at(&self, addr: u64) -> Option<i32> {
+		let pc = self.pc_at(addr)?;
+
+		self.debug_info.line_info.as_ref()?.line_at(pc)
+	}
+
+	pub fn local_at(&self, register: u8, addr: u64) -> Option<&Local> {
+		let pc = self.pc_at(addr)?;
+
+		self.debug_info
+			.local_list
+			.iter()
+			.rev()
+			.find(|v| v.register == register && (v.start_pc..v.end_pc).contains(&pc))
+	}
+
+	pub fn param_at(&self, register: u8) -> Option<&Local> {
+		self.debug_info
+			.local_list
+			.iter()
+			.find(|v| v.register == register && v.start_pc == 0)
+	}
+
+	// Binary Ninja names a register once per function, so only registers that keep
+	// the same local name through every live range get one
+	pub fn register_name(&self, register: u8) -> Option<usize> {
+		let mut name_list = self
+			.debug_info
+			.local_list
+			.iter()
+			.filter(|v| v.register == register)
+			.map(|v| v.name);
+
+		let name = name_list.next()?;
+
+		name_list.all(|v| v == name).then_some(name)
+	}
+
+	pub fn upvalue_at(&self, upvalue: u8) -> Option<usize> {
+		self.debug_info
+			.upvalue_list
+			.get(usize::from(upvalue))
+			.copied()
+	}
 }
 
 #[derive(Default)]
 pub struct Module {
+	format: Format,
 	function_list: List<Function>,
-	string_list: List<Range>,
-	start_id: usize,
+	string_list: List<Str>,
+	userdata_list: List<UserDataType>,
+	start_id: Option<usize>,
+	compile_error: Option<Str>,
+	proto_tree: ProtoTree,
+	inferred_name_map: HashMap<usize, String>,
 }
 
 fn cmp_range_to_usize(range: Range, value: usize) -> Ordering {
@@ -90,26 +317,126 @@ fn cmp_range_to_usize(range: Range, value: usize) -> Ordering {
 }
 
 impl Module {
-	pub fn new(function_list: List<Function>, string_list: List<Range>, start_id: usize) -> Self {
+	pub fn new(
+		format: Format,
+		function_list: List<Function>,
+		string_list: List<Str>,
+		userdata_list: List<UserDataType>,
+		start_id: Option<usize>,
+	) -> Self {
+		let proto_tree = ProtoTree::new(&function_list.data);
+
 		Self {
+			format,
 			function_list,
 			string_list,
+			userdata_list,
 			start_id,
+			compile_error: None,
+			proto_tree,
+			inferred_name_map: HashMap::new(),
+		}
+	}
+
+	pub fn with_error(format: Format, compile_error: Str) -> Self {
+		Self {
+			format,
+			compile_error: Some(compile_error),
+			..Self::default()
 		}
 	}
 
+	pub fn format(&self) -> Format {
+		self.format
+	}
+
+	pub fn set_encoding(&mut self, encoding: Encoding) {
+		self.format.encoding = encoding;
+	}
+
 	pub fn function_list(&self) -> &List<Function> {
 		&self.function_list
 	}
 
-	pub fn string_list(&self) -> &List<Range> {
+	pub fn string_list(&self) -> &List<Str> {
 		&self.string_list
 	}
 
-	pub fn entry_point(&self) -> u64 {
-		let func = &self.function_list().data[self.start_id];
+	pub fn string_at(&self, index: usize) -> Option<&Str> {
+		let adjusted = index.checked_sub(1)?;
+
+		self.string_list.data.get(adjusted)
+	}
+
+	pub fn userdata_list(&self) -> &List<UserDataType> {
+		&self.userdata_list
+	}
+
+	// Tagged userdata types refer to the table by zero based index
+	pub fn userdata_name(&self, tag: u8) -> Option<&Str> {
+		let index = tag.checked_add(1)?;
+		let userdata = self.userdata_list.data.iter().find(|v| v.index == index)?;
+
+		self.string_at(userdata.name)
+	}
+
+	pub fn start_id(&self) -> Option<usize> {
+		self.start_id
+	}
+
+	pub fn proto_tree(&self) -> &ProtoTree {
+		&self.proto_tree
+	}
+
+	pub fn inferred_name_map(&self) -> &HashMap<usize, String> {
+		&self.inferred_name_map
+	}
+
+	pub fn set_inferred_names(&mut self, name_map: HashMap<usize, String>) {
+		self.inferred_name_map = name_map;
+	}
+
+	pub fn function_name(&self, index: usize) -> Cow<'_, str> {
+		let name = self
+			.function_list
+			.data
+			.get(index)
+			.and_then(|func| self.string_at(func.name()));
+
+		if let Some(name) = name {
+			return name.to_string_lossy();
+		}
+
+		match self.inferred_name_map.get(&index) {
+			Some(name) => name.into(),
+			None if self.start_id == Some(index) => "main".into(),
+			None => format!("func_{index}").into(),
+		}
+	}
+
+	pub fn qualified_name(&self, index: usize) -> String {
+		let name_list: Vec<_> = self
+			.proto_tree
+			.path_of(index)
+			.into_iter()
+			.map(|v| self.function_name(v))
+			.collect();
 
-		func.code().start as u64
+		name_list.join(" > ")
+	}
+
+	pub fn is_complete(&self) -> bool {
+		self.start_id.is_some() || self.compile_error.is_some()
+	}
+
+	pub fn compile_error(&self) -> Option<&Str> {
+		self.compile_error.as_ref()
+	}
+
+	pub fn entry_point(&self) -> Option<u64> {
+		let func = self.function_list().data.get(self.start_id?)?;
+
+		Some(func.code().start as u64)
 	}
 
 	pub fn by_address(&self, addr: u64) -> Option<&Function> {
@@ -122,3 +449,26 @@ impl Module {
 			.ok()
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use crate::file::{parser::parse_bytes, sample::hello};
+
+	#[test]
+	fn register_name() {
+		let mut sample = hello(6);
+		let module = parse_bytes(&sample.build()).unwrap();
+		let main = &module.function_list().data[1];
+
+		assert_eq!(main.register_name(2), Some(4));
+		assert_eq!(main.register_name(1), None);
+
+		// The same register reused by another local has no single name
+		sample.function_list[1].local_list.push((2, 1, 3, 2));
+
+		let module = parse_bytes(&sample.build()).unwrap();
+		let main = &module.function_list().data[1];
+
+		assert_eq!(main.register_name(2), None);
+	}
+}
@@ -0,0 +1,100 @@
+use std::fmt::{Display, Formatter, Result as FResult};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+	Buffer,
+	Header,
+	StringList,
+	UserDataList,
+	FunctionList,
+	EntryPoint,
+}
+
+impl Stage {
+	pub const fn name(self) -> &'static str {
+		match self {
+			Self::Buffer => "buffer",
+			Self::Header => "header",
+			Self::StringList => "string list",
+			Self::UserDataList => "userdata type list",
+			Self::FunctionList => "function list",
+			Self::EntryPoint => "entry point",
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+	stage: Stage,
+	position: usize,
+	function: Option<usize>,
+	cause: std::io::Error,
+}
+
+impl ParseError {
+	pub fn new(stage: Stage, position: usize, cause: std::io::Error) -> Self {
+		Self {
+			stage,
+			position,
+			function: None,
+			cause,
+		}
+	}
+
+	pub fn with_function(mut self, function: usize) -> Self {
+		self.function = Some(function);
+
+		self
+	}
+
+	pub fn stage(&self) -> Stage {
+		self.stage
+	}
+}
+
+impl Display for ParseError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+		let name = self.stage.name();
+
+		write!(f, "failed to parse {name} at offset {:#x}", self.position)?;
+
+		if let Some(function) = self.function {
+			write!(f, " in function {function}")?;
+		}
+
+		write!(f, ": {}", self.cause)
+	}
+}
+
+impl std::error::Error for ParseError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		Some(&self.cause)
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WriteError {
+	Incomplete,
+	NoString(usize),
+	NoFunction(usize),
+	NoConstant(usize, usize),
+	NotNumber(usize, usize),
+}
+
+impl Display for WriteError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+		match *self {
+			Self::Incomplete => write!(f, "module is incomplete"),
+			Self::NoString(index) => write!(f, "there is no string {index}"),
+			Self::NoFunction(index) => write!(f, "there is no function {index}"),
+			Self::NoConstant(function, index) => {
+				write!(f, "function {function} has no constant {index}")
+			}
+			Self::NotNumber(function, index) => {
+				write!(f, "constant {index} of function {function} is not a number")
+			}
+		}
+	}
+}
+
+impl std::error::Error for WriteError {}
@@ -0,0 +1,110 @@
+use std::{error::Error, fs};
+
+use binaryninja::{
+	binaryview::{BinaryView, BinaryViewBase, BinaryViewExt},
+	command::Command,
+	interaction::{get_integer_input, get_save_filename_input, get_text_line_input},
+};
+use log::{error, info};
+
+use super::{parser::parse_bytes, view::VIEW_NAME, writer::Writer};
+
+type EditResult = Result<(), Box<dyn Error>>;
+
+// Saving only makes sense from the Luau view, which knows where the module came from
+pub struct SaveCommand(pub fn(&BinaryView));
+
+impl Command for SaveCommand {
+	fn action(&self, view: &BinaryView) {
+		(self.0)(view);
+	}
+
+	fn valid(&self, view: &BinaryView) -> bool {
+		view.view_type().as_str() == VIEW_NAME
+	}
+}
+
+// Patches made in the Luau view are written through to the raw parent view
+fn read_source(view: &BinaryView) -> Option<Vec<u8>> {
+	let parent = view.parent_view().ok()?;
+
+	Some(parent.read_vec(0, parent.len()))
+}
+
+// Edits return `None` when the user cancels an input prompt
+fn save_with<E>(view: &BinaryView, edit: E)
+where
+	E: FnOnce(&mut Writer) -> Option<EditResult>,
+{
+	let data = match read_source(view) {
+		Some(data) => data,
+		None => return error!("Could not read Luau module"),
+	};
+
+	let module = match parse_bytes(&data) {
+		Ok(module) => module,
+		Err(e) => return error!("Could not save Luau module, {e}"),
+	};
+
+	let mut writer = Writer::new(&data, &module);
+
+	match edit(&mut writer) {
+		Some(Ok(())) => {}
+		Some(Err(e)) => return error!("Could not edit Luau module, {e}"),
+		None => return,
+	}
+
+	let result = match writer.write() {
+		Ok(result) => result,
+		Err(e) => return error!("Could not save Luau module, {e}"),
+	};
+
+	let path = match get_save_filename_input("Save bytecode as", "", "") {
+		Some(path) => path,
+		None => return,
+	};
+
+	match fs::write(&path, result) {
+		Ok(()) => info!("Saved Luau module to {}", path.display()),
+		Err(e) => error!("Could not write {}, {e}", path.display()),
+	}
+}
+
+fn replace_string(writer: &mut Writer, index: i64, text: &str) -> EditResult {
+	writer.replace_string(index.try_into()?, text.as_bytes())?;
+
+	Ok(())
+}
+
+fn replace_number(writer: &mut Writer, function: i64, constant: i64, value: &str) -> EditResult {
+	writer.replace_number(
+		function.try_into()?,
+		constant.try_into()?,
+		value.trim().parse()?,
+	)?;
+
+	Ok(())
+}
+
+pub fn save_bytecode(view: &BinaryView) {
+	save_with(view, |_| Some(Ok(())));
+}
+
+pub fn save_with_string(view: &BinaryView) {
+	save_with(view, |writer| {
+		let index = get_integer_input("String index (as in str_N)", "Replace String")?;
+		let text = get_text_line_input("New contents", "Replace String")?;
+
+		Some(replace_string(writer, index, &text))
+	});
+}
+
+pub fn save_with_number(view: &BinaryView) {
+	save_with(view, |writer| {
+		let function = get_integer_input("Function index (as in code_N)", "Replace Number")?;
+		let constant = get_integer_input("Constant index", "Replace Number")?;
+		let value = get_text_line_input("New value", "Replace Number")?;
+
+		Some(replace_number(writer, function, constant, &value))
+	});
+}
@@ -1,3 +1,21 @@
+pub mod code;
+#[cfg(feature = "plugin")]
+pub mod component;
 pub mod data;
+pub mod error;
+#[cfg(feature = "plugin")]
+pub mod export;
+pub mod naming;
 pub mod parser;
+#[cfg(feature = "plugin")]
+pub mod persist;
+pub mod registry;
+#[cfg(test)]
+mod sample;
+pub mod snapshot;
+pub mod tree;
+pub mod validate;
+pub mod verify;
+#[cfg(feature = "plugin")]
 pub mod view;
+pub mod writer;
@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+
+use crate::decoder::{encoding::Encoding, import::Import, inst::Inst, opcode::Opcode};
+
+use super::data::{Function, Module, Value};
+
+struct Namer<'a> {
+	module: &'a Module,
+	func: &'a Function,
+	desc_map: HashMap<u8, String>,
+	closure_map: HashMap<u8, usize>,
+	name_map: HashMap<usize, String>,
+}
+
+impl<'a> Namer<'a> {
+	fn string_constant(&self, index: usize) -> Option<String> {
+		match self.func.constant_list().data.get(index)? {
+			Value::String(index) => Some(self.module.string_at(*index)?.to_string_lossy().into()),
+			_ => None,
+		}
+	}
+
+	fn aux_constant(&self, inst: Inst) -> Option<String> {
+		self.string_constant(inst.adjacent()? as usize)
+	}
+
+	fn import_path(&self, encoded: u32) -> Option<String> {
+		let name_list = Import::from(encoded)
+			.map(|v| self.string_constant(v))
+			.collect::<Option<Vec<_>>>()?;
+
+		Some(name_list.join("."))
+	}
+
+	// Registers fall back to the name of the local they hold, if any
+	fn desc_of(&self, register: u8, addr: u64) -> Option<String> {
+		if let Some(desc) = self.desc_map.get(&register) {
+			return Some(desc.clone());
+		}
+
+		let local = self.func.local_at(register, addr)?;
+
+		Some(self.module.string_at(local.name)?.to_string_lossy().into())
+	}
+
+	fn name_closure(&mut self, register: u8, name: Option<String>) {
+		if let (Some(&proto), Some(name)) = (self.closure_map.get(&register), name) {
+			self.name_map.entry(proto).or_insert(name);
+		}
+	}
+
+	fn clear(&mut self, register: u8) {
+		self.desc_map.remove(&register);
+		self.closure_map.remove(&register);
+	}
+
+	fn visit_call(&mut self, inst: Inst, addr: u64) {
+		let base = inst.a();
+		let end = match inst.b() {
+			0 => self.func.max_stack_size(),
+			b => base.saturating_add(b),
+		};
+
+		let callee = self.desc_of(base, addr);
+
+		for register in base.saturating_add(1)..end {
+			let name = callee.as_ref().map(|v| format!("{v}@cb"));
+
+			self.name_closure(register, name);
+		}
+
+		for register in base..self.func.max_stack_size() {
+			self.clear(register);
+		}
+	}
+
+	fn visit(&mut self, inst: Inst, addr: u64) {
+		let a = inst.a();
+
+		match inst.op() {
+			Opcode::NewClosure => {
+				let proto = self
+					.func
+					.reference_list()
+					.data
+					.get(inst.d() as usize)
+					.copied();
+
+				self.clear(a);
+
+				if let Some(proto) = proto {
+					self.closure_map.insert(a, proto);
+				}
+			}
+			Opcode::DupClosure => {
+				let value = self.func.constant_list().data.get(inst.d() as usize);
+
+				self.clear(a);
+
+				if let Some(&Value::Closure(proto)) = value {
+					self.closure_map.insert(a, proto);
+				}
+			}
+			Opcode::Move => {
+				let desc = self.desc_of(inst.b(), addr);
+				let proto = self.closure_map.get(&inst.b()).copied();
+
+				self.clear(a);
+
+				if let Some(desc) = desc {
+					self.desc_map.insert(a, desc);
+				}
+
+				if let Some(proto) = proto {
+					self.closure_map.insert(a, proto);
+				}
+			}
+			Opcode::GetGlobal => {
+				let desc = self.aux_constant(inst);
+
+				self.clear(a);
+
+				if let Some(desc) = desc {
+					self.desc_map.insert(a, desc);
+				}
+			}
+			Opcode::GetImport => {
+				let desc = inst.adjacent().and_then(|v| self.import_path(v as u32));
+
+				self.clear(a);
+
+				if let Some(desc) = desc {
+					self.desc_map.insert(a, desc);
+				}
+			}
+			Opcode::GetTableKey => {
+				let table = self.desc_of(inst.b(), addr);
+				let key = self.aux_constant(inst);
+
+				self.clear(a);
+
+				if let (Some(table), Some(key)) = (table, key) {
+					self.desc_map.insert(a, format!("{table}.{key}"));
+				}
+			}
+			Opcode::NameCall => {
+				let object = self.desc_of(inst.b(), addr);
+				let method = self.aux_constant(inst);
+
+				self.clear(a);
+				self.clear(a.saturating_add(1));
+
+				if let (Some(object), Some(method)) = (object, method) {
+					self.desc_map.insert(a, format!("{object}:{method}"));
+				}
+			}
+			Opcode::SetGlobal => {
+				let name = self.aux_constant(inst);
+
+				self.name_closure(a, name);
+			}
+			Opcode::SetTableKey => {
+				let key = self.aux_constant(inst);
+				let name = match (self.desc_of(inst.b(), addr), key) {
+					(Some(table), Some(key)) => Some(format!("{table}.{key}")),
+					(None, key) => key,
+					_ => None,
+				};
+
+				self.name_closure(a, name);
+			}
+			Opcode::SetUpValue => {
+				let name = self
+					.func
+					.upvalue_at(inst.b())
+					.and_then(|v| self.module.string_at(v))
+					.map(|v| v.to_string_lossy().into());
+
+				self.name_closure(a, name);
+			}
+			Opcode::Call => self.visit_call(inst, addr),
+			// Anything else that writes its first register invalidates what we knew about it
+			Opcode::LoadNil
+			| Opcode::LoadBoolean
+			| Opcode::LoadInteger
+			| Opcode::LoadConstant
+			| Opcode::LoadConstantEx
+			| Opcode::GetUpValue
+			| Opcode::GetTable
+			| Opcode::GetTableIndex
+			| Opcode::NewTable
+			| Opcode::DupTable
+			| Opcode::GetVariadic
+			| Opcode::Add
+			| Opcode::Sub
+			| Opcode::Mul
+			| Opcode::Div
+			| Opcode::Mod
+			| Opcode::Pow
+			| Opcode::AddConstant
+			| Opcode::SubConstant
+			| Opcode::MulConstant
+			| Opcode::DivConstant
+			| Opcode::ModConstant
+			| Opcode::PowConstant
+			| Opcode::FloorDiv
+			| Opcode::FloorDivConstant
+			| Opcode::ReverseSubConstant
+			| Opcode::ReverseDivConstant
+			| Opcode::And
+			| Opcode::Or
+			| Opcode::AndConstant
+			| Opcode::OrConstant
+			| Opcode::Concat
+			| Opcode::Not
+			| Opcode::Minus
+			| Opcode::Length => self.clear(a),
+			_ => {}
+		}
+	}
+
+	fn run(&mut self, mut code: &[u8], encoding: Encoding, version: u8) {
+		let mut addr = self.func.code().start as u64;
+
+		while let Ok(inst) = Inst::decode(code, encoding, version) {
+			let len = inst.op().size();
+
+			self.visit(inst, addr);
+
+			code = &code[len..];
+			addr += len as u64;
+		}
+	}
+}
+
+// Follows closures into the global, field, upvalue or call argument they end up in,
+// giving names like `Module.Init` or `Players.PlayerAdded:Connect@cb`
+pub fn infer_names(data: &[u8], module: &Module) -> HashMap<usize, String> {
+	let format = module.format();
+	let mut name_map = HashMap::new();
+
+	for func in module.function_list().data.iter() {
+		let code = match data.get(func.code()) {
+			Some(code) => code,
+			None => continue,
+		};
+
+		let mut namer = Namer {
+			module,
+			func,
+			desc_map: HashMap::new(),
+			closure_map: HashMap::new(),
+			name_map,
+		};
+
+		namer.run(code, format.encoding, format.version);
+
+		name_map = namer.name_map;
+	}
+
+	// Real debug names always win over inferred ones
+	name_map.retain(|&index, _| {
+		let func = module.function_list().data.get(index);
+
+		func.is_some_and(|func| module.string_at(func.name()).is_none())
+	});
+
+	name_map
+}
+
+#[cfg(test)]
+mod test {
+	use super::infer_names;
+	use crate::file::{
+		parser::parse_bytes,
+		sample::{import, inst, inst_d, Constant, Sample, SampleFunction},
+	};
+
+	// `game.Players.PlayerAdded:Connect(function() end)`
+	#[test]
+	fn name_callback() {
+		let callback = SampleFunction {
+			code: vec![inst(22, 0, 1, 0)],
+			..SampleFunction::default()
+		};
+
+		let main = SampleFunction {
+			max_stack_size: 3,
+			is_vararg: true,
+			code: vec![
+				inst(12, 0, 0, 0),
+				import(&[1, 2]),
+				inst(20, 0, 0, 0),
+				3,
+				inst_d(19, 2, 0),
+				inst(21, 0, 3, 1),
+				inst(22, 0, 1, 0),
+			],
+			constant_list: vec![
+				Constant::Import(import(&[1, 2])),
+				Constant::String(1),
+				Constant::String(2),
+				Constant::String(3),
+			],
+			reference_list: vec![0],
+			..SampleFunction::default()
+		};
+
+		let sample = Sample {
+			version: 6,
+			type_version: 1,
+			string_list: vec![b"Players", b"PlayerAdded", b"Connect"],
+			userdata_list: Vec::new(),
+			function_list: vec![callback, main],
+			start_id: 1,
+		};
+
+		let data = sample.build();
+		let module = parse_bytes(&data).unwrap();
+		let name_map = infer_names(&data, &module);
+
+		assert_eq!(name_map.len(), 1);
+		assert_eq!(name_map[&0], "Players.PlayerAdded:Connect@cb");
+	}
+}
@@ -3,19 +3,35 @@ use std::{
 	ops::Range,
 };
 
-use binaryninja::binaryview::{BinaryView, BinaryViewBase, BinaryViewExt};
 use num_enum::TryFromPrimitive;
 
-use super::data::{Function, List, Module, Value};
+use crate::decoder::{encoding::Encoding, inst::Inst};
 
-type PResult<T> = std::io::Result<T>;
-type Stream<'a> = Cursor<&'a [u8]>;
+use super::{
+	data::{
+		DebugInfo, Format, Function, LineInfo, List, Local, MetaData, Module, Str, TypeInfo,
+		TypeKind, TypeTag, TypedLocal, UserDataType, Value,
+	},
+	error::{ParseError, Stage},
+};
+
+pub type PResult<T> = std::io::Result<T>;
+pub type Stream<'a> = Cursor<&'a [u8]>;
+
+const LUAU_VERSION_MIN: u8 = 3;
+const LUAU_VERSION_MAX: u8 = 6;
 
-const LUAU_VERSION: u8 = 3;
+const LUAU_TYPE_VERSION_MIN: u8 = 1;
+const LUAU_TYPE_VERSION_MAX: u8 = 3;
+
+const TYPE_FUNCTION: u8 = 5;
+pub const TYPE_TAGGED_USERDATA_BASE: u8 = 64;
+const TYPE_TAGGED_USERDATA_END: u8 = 64 + 32;
+pub const TYPE_OPTIONAL_BIT: u8 = 1 << 7;
 
 #[repr(u8)]
 #[derive(TryFromPrimitive)]
-enum TypeConstant {
+pub enum TypeConstant {
 	Nil = 0,
 	Boolean,
 	Number,
@@ -23,13 +39,25 @@ enum TypeConstant {
 	Import,
 	Table,
 	Closure,
+	Vector,
 }
 
 fn position_of(stream: &Stream) -> usize {
 	stream.position().try_into().expect("Position out of range")
 }
 
-fn parse_u8(s: &mut Stream) -> PResult<u8> {
+fn remaining_of(stream: &Stream) -> usize {
+	stream.get_ref().len().saturating_sub(position_of(stream))
+}
+
+fn in_stage<P, O>(s: &mut Stream, stage: Stage, parse: P) -> Result<O, ParseError>
+where
+	P: FnOnce(&mut Stream) -> PResult<O>,
+{
+	parse(s).map_err(|cause| ParseError::new(stage, position_of(s), cause))
+}
+
+pub fn parse_u8(s: &mut Stream) -> PResult<u8> {
 	let mut buf = [0_u8; 1];
 
 	s.read_exact(&mut buf)?;
@@ -37,7 +65,7 @@ fn parse_u8(s: &mut Stream) -> PResult<u8> {
 	Ok(buf[0])
 }
 
-fn parse_u32(s: &mut Stream) -> PResult<u32> {
+pub fn parse_u32(s: &mut Stream) -> PResult<u32> {
 	let mut buf = [0_u8; 4];
 
 	s.read_exact(&mut buf)?;
@@ -45,7 +73,7 @@ fn parse_u32(s: &mut Stream) -> PResult<u32> {
 	Ok(u32::from_le_bytes(buf))
 }
 
-fn parse_u64(s: &mut Stream) -> PResult<u64> {
+pub fn parse_u64(s: &mut Stream) -> PResult<u64> {
 	let mut buf = [0_u8; 8];
 
 	s.read_exact(&mut buf)?;
@@ -53,20 +81,23 @@ fn parse_u64(s: &mut Stream) -> PResult<u64> {
 	Ok(u64::from_le_bytes(buf))
 }
 
-fn parse_any_size(s: &mut Stream) -> PResult<usize> {
+// Every continuation byte adds 7 bits, anything longer than a `usize` is corrupt
+const ANY_SIZE_MAX_LEN: u32 = usize::BITS.div_ceil(7);
+
+pub fn parse_any_size(s: &mut Stream) -> PResult<usize> {
 	let mut result = 0;
 
-	for i in 0.. {
+	for i in 0..ANY_SIZE_MAX_LEN {
 		let v = parse_u8(s)? as usize;
 
 		result |= (v & 0x7F) << (i * 7);
 
 		if v & 0x80 == 0 {
-			break;
+			return Ok(result);
 		}
 	}
 
-	Ok(result)
+	Err(Error::new(ErrorKind::InvalidData, "Variable size too long"))
 }
 
 fn parse_list_of<P, O>(s: &mut Stream, parse: P) -> PResult<List<O>>
@@ -75,9 +106,11 @@ where
 {
 	let start = position_of(s);
 	let len = parse_any_size(s)?;
-	let mut temp = Vec::with_capacity(len);
+	let mut temp = Vec::with_capacity(len.min(remaining_of(s)));
+	let mut offset_list = Vec::with_capacity(temp.capacity());
 
 	for _ in 0..len {
+		offset_list.push(position_of(s));
 		temp.push(parse(s)?);
 	}
 
@@ -85,22 +118,12 @@ where
 
 	Ok(List {
 		data: temp.into(),
+		offset_list: offset_list.into(),
 		range: start..end,
 	})
 }
 
-fn parse_list_ignored<P, O>(s: &mut Stream, parse: P) -> PResult<()>
-where
-	P: Fn(&mut Stream) -> PResult<O>,
-{
-	for _ in 0..parse_any_size(s)? {
-		parse(s)?;
-	}
-
-	Ok(())
-}
-
-fn parse_string_data(s: &mut Stream) -> PResult<Range<usize>> {
+pub fn parse_sized_data(s: &mut Stream) -> PResult<Range<usize>> {
 	let len = parse_any_size(s)?;
 	let start = position_of(s);
 
@@ -111,13 +134,149 @@ fn parse_string_data(s: &mut Stream) -> PResult<Range<usize>> {
 	Ok(start..position_of(s))
 }
 
-fn parse_func_meta_data(s: &mut Stream) -> PResult<()> {
-	let _max_stack_size = parse_u8(s)?;
-	let _num_param = parse_u8(s)?;
-	let _num_upval = parse_u8(s)?;
-	let _is_vararg = parse_u8(s)?;
+fn parse_string(s: &mut Stream) -> PResult<Str> {
+	let range = parse_sized_data(s)?;
+	let data = s.get_ref()[range.clone()].into();
+
+	Ok(Str { range, data })
+}
+
+// Version 3 type info lists userdata type names, terminated by a zero index
+fn parse_userdata_list(format: Format, s: &mut Stream) -> PResult<List<UserDataType>> {
+	let start = position_of(s);
+	let mut temp = Vec::new();
+	let mut offset_list = Vec::new();
+
+	if format.type_version == 3 {
+		loop {
+			let offset = position_of(s);
+			let index = parse_u8(s)?;
+
+			if index == 0 {
+				break;
+			}
+
+			let name = parse_any_size(s)?;
+
+			temp.push(UserDataType { index, name });
+			offset_list.push(offset);
+		}
+	}
+
+	Ok(List {
+		data: temp.into(),
+		offset_list: offset_list.into(),
+		range: start..position_of(s),
+	})
+}
+
+pub fn parse_type_tag(s: &mut Stream) -> PResult<TypeTag> {
+	let data = parse_u8(s)?;
+	let kind = match data & !TYPE_OPTIONAL_BIT {
+		0 => TypeKind::Nil,
+		1 => TypeKind::Boolean,
+		2 => TypeKind::Number,
+		3 => TypeKind::String,
+		4 => TypeKind::Table,
+		5 => TypeKind::Function,
+		6 => TypeKind::Thread,
+		7 => TypeKind::UserData,
+		8 => TypeKind::Vector,
+		9 => TypeKind::Buffer,
+		15 => TypeKind::Any,
+		v @ TYPE_TAGGED_USERDATA_BASE..TYPE_TAGGED_USERDATA_END => {
+			TypeKind::Tagged(v - TYPE_TAGGED_USERDATA_BASE)
+		}
+		v => TypeKind::Unknown(v),
+	};
+
+	Ok(TypeTag {
+		kind,
+		is_optional: data & TYPE_OPTIONAL_BIT != 0,
+	})
+}
+
+fn parse_function_type(s: &mut Stream) -> PResult<Box<[TypeTag]>> {
+	if parse_u8(s)? != TYPE_FUNCTION {
+		return Err(Error::new(ErrorKind::InvalidData, "Invalid function type"));
+	}
+
+	let len = parse_u8(s)?;
+
+	(0..len).map(|_| parse_type_tag(s)).collect()
+}
+
+fn parse_typed_local(s: &mut Stream) -> PResult<TypedLocal> {
+	let tag = parse_type_tag(s)?;
+	let register = parse_u8(s)?;
+	let start_pc = parse_any_size(s)?;
+	let len = parse_any_size(s)?;
+
+	Ok(TypedLocal {
+		tag,
+		register,
+		start_pc,
+		end_pc: start_pc.saturating_add(len),
+	})
+}
+
+fn parse_type_info(format: Format, s: &mut Stream) -> PResult<TypeInfo> {
+	if format.type_version == 1 {
+		let param_list = parse_function_type(s)?;
+
+		return Ok(TypeInfo {
+			param_list,
+			..TypeInfo::default()
+		});
+	}
+
+	let function_len = parse_any_size(s)?;
+	let upvalue_len = parse_any_size(s)?;
+	let local_len = parse_any_size(s)?;
 
-	Ok(())
+	let param_list = if function_len == 0 {
+		Box::default()
+	} else {
+		parse_function_type(s)?
+	};
+
+	let upvalue_list = (0..upvalue_len)
+		.map(|_| parse_type_tag(s))
+		.collect::<PResult<_>>()?;
+
+	let local_list = (0..local_len)
+		.map(|_| parse_typed_local(s))
+		.collect::<PResult<_>>()?;
+
+	Ok(TypeInfo {
+		param_list,
+		upvalue_list,
+		local_list,
+	})
+}
+
+fn parse_func_meta_data(format: Format, s: &mut Stream) -> PResult<MetaData> {
+	let mut meta_data = MetaData {
+		max_stack_size: parse_u8(s)?,
+		num_param: parse_u8(s)?,
+		num_upval: parse_u8(s)?,
+		is_vararg: parse_u8(s)? != 0,
+		..MetaData::default()
+	};
+
+	if format.version >= 4 {
+		meta_data.flags = parse_u8(s)?;
+		meta_data.type_range = parse_sized_data(s)?;
+
+		// Type info is only a hint, so a malformed blob should not reject the function
+		if !meta_data.type_range.is_empty() {
+			let mut blob = Cursor::new(&s.get_ref()[meta_data.type_range.clone()]);
+
+			meta_data.type_info = parse_type_info(format, &mut blob).ok();
+		}
+	}
+
+	Ok(meta_data)
 }
 
 fn parse_code(s: &mut Stream) -> PResult<Range<usize>> {
@@ -131,7 +290,7 @@ fn parse_code(s: &mut Stream) -> PResult<Range<usize>> {
 	Ok(start..position_of(s))
 }
 
-fn parse_constant(s: &mut Stream) -> PResult<Value> {
+pub fn parse_constant(s: &mut Stream) -> PResult<Value> {
 	let tag = parse_u8(s)?
 		.try_into()
 		.map_err(|_| Error::new(ErrorKind::InvalidData, "Invalid constant tag"))?;
@@ -162,73 +321,110 @@ fn parse_constant(s: &mut Stream) -> PResult<Value> {
 			Value::Import(data)
 		}
 		TypeConstant::Table => {
-			parse_list_ignored(s, parse_any_size)?;
+			let key_list = parse_list_of(s, parse_any_size)?;
 
-			Value::Table
+			Value::Table(key_list.data)
 		}
 		TypeConstant::Closure => {
 			let index = parse_any_size(s)?;
 
 			Value::Closure(index)
 		}
+		TypeConstant::Vector => {
+			let mut data = [0.0; 4];
+
+			for value in &mut data {
+				*value = f32::from_bits(parse_u32(s)?);
+			}
+
+			Value::Vector(data)
+		}
 	};
 
 	Ok(value)
 }
 
-fn parse_line_info(len: usize, s: &mut Stream) -> PResult<()> {
-	let line_gap = parse_u8(s)?;
-	let interval = ((len - 1) >> line_gap) + 1;
+fn parse_line_info(len: usize, s: &mut Stream) -> PResult<LineInfo> {
+	let gap_log2 = parse_u8(s)?;
+	let interval = len
+		.checked_sub(1)
+		.and_then(|v| v.checked_shr(gap_log2.into()))
+		.map_or(0, |v| v + 1);
+
+	let mut offset_list = Vec::with_capacity(len);
+	let mut last_offset = 0_u8;
 
 	for _ in 0..len {
-		parse_u8(s)?;
+		last_offset = last_offset.wrapping_add(parse_u8(s)?);
+		offset_list.push(last_offset);
 	}
 
+	let mut line_list = Vec::with_capacity(interval);
+	let mut last_line = 0_i32;
+
 	for _ in 0..interval {
-		parse_u32(s)?;
+		last_line = last_line.wrapping_add(parse_u32(s)? as i32);
+		line_list.push(last_line);
 	}
 
-	Ok(())
+	Ok(LineInfo {
+		gap_log2,
+		offset_list: offset_list.into(),
+		line_list: line_list.into(),
+	})
 }
 
-fn parse_local_info(s: &mut Stream) -> PResult<()> {
-	let _name = parse_any_size(s)?;
-	let _start_pc = parse_any_size(s)?;
-	let _end_pc = parse_any_size(s)?;
-	let _reg = parse_u8(s)?;
-
-	Ok(())
+fn parse_local_info(s: &mut Stream) -> PResult<Local> {
+	let name = parse_any_size(s)?;
+	let start_pc = parse_any_size(s)?;
+	let end_pc = parse_any_size(s)?;
+	let register = parse_u8(s)?;
+
+	Ok(Local {
+		name,
+		start_pc,
+		end_pc,
+		register,
+	})
 }
 
-fn parse_debug_info(len: usize, s: &mut Stream) -> PResult<()> {
+fn parse_debug_info(len: usize, s: &mut Stream) -> PResult<DebugInfo> {
 	let has_line_info = parse_u8(s)? != 0;
-
-	if has_line_info {
-		parse_line_info(len, s)?;
-	}
+	let line_info = if has_line_info {
+		Some(parse_line_info(len, s)?)
+	} else {
+		None
+	};
 
 	let has_var_info = parse_u8(s)? != 0;
+	let (local_list, upvalue_list) = if has_var_info {
+		let local_list = parse_list_of(s, parse_local_info)?;
+		let upvalue_list = parse_list_of(s, parse_any_size)?;
 
-	if has_var_info {
-		parse_list_ignored(s, parse_local_info)?;
-		parse_list_ignored(s, parse_any_size)?;
-	}
+		(local_list.data, upvalue_list.data)
+	} else {
+		Default::default()
+	};
 
-	Ok(())
+	Ok(DebugInfo {
+		line_info,
+		local_list,
+		upvalue_list,
+	})
 }
 
-fn parse_function(s: &mut Stream) -> PResult<Function> {
+fn parse_function(format: Format, s: &mut Stream) -> PResult<Function> {
 	let start = position_of(s);
-
-	parse_func_meta_data(s)?;
+	let mut meta_data = parse_func_meta_data(format, s)?;
 
 	let code = parse_code(s)?;
 	let constant_list = parse_list_of(s, parse_constant)?;
 	let reference_list = parse_list_of(s, parse_any_size)?;
-	let _line_defined = parse_any_size(s)?;
-	let debug_name = parse_any_size(s)?;
 
-	parse_debug_info(code.len() / 4, s)?;
+	meta_data.line_defined = parse_any_size(s)?;
+
+	let debug_name = parse_any_size(s)?;
+	let debug_info = parse_debug_info(code.len() / 4, s)?;
 
 	let end = position_of(s);
 
@@ -238,29 +434,261 @@ fn parse_function(s: &mut Stream) -> PResult<Function> {
 		code,
 		constant_list,
 		reference_list,
+		meta_data,
+		debug_info,
 	))
 }
 
-fn parse_module(s: &mut Stream) -> PResult<Module> {
+fn parse_format(s: &mut Stream) -> PResult<Format> {
 	let version = parse_u8(s)?;
 
-	if version != LUAU_VERSION {
+	if version != 0 && !(LUAU_VERSION_MIN..=LUAU_VERSION_MAX).contains(&version) {
 		return Err(Error::new(ErrorKind::InvalidData, "Invalid module version"));
 	}
 
-	let string_list = parse_list_of(s, parse_string_data)?;
-	let function_list = parse_list_of(s, parse_function)?;
-	let entry_point = parse_any_size(s)?;
+	let type_version = if version >= 4 {
+		let type_version = parse_u8(s)?;
+
+		if !(LUAU_TYPE_VERSION_MIN..=LUAU_TYPE_VERSION_MAX).contains(&type_version) {
+			return Err(Error::new(ErrorKind::InvalidData, "Invalid types version"));
+		}
+
+		type_version
+	} else {
+		0
+	};
+
+	Ok(Format {
+		version,
+		type_version,
+		encoding: Encoding::Plain,
+	})
+}
+
+// The compiler formats its errors as `:<line>: <message>`
+fn is_compile_error(text: &str) -> bool {
+	let Some(rest) = text.strip_prefix(':') else {
+		return false;
+	};
+
+	let message = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+
+	message.len() < rest.len()
+		&& message.starts_with(':')
+		&& !message.contains(|c: char| c.is_control() && !c.is_whitespace())
+}
+
+// Failed compilations produce a version of 0 followed by the error message
+fn parse_compile_error(s: &mut Stream) -> PResult<Str> {
+	let start = position_of(s);
+	let end = s.get_ref().len();
+	let data: Box<[u8]> = s.get_ref()[start..end].into();
+
+	if !std::str::from_utf8(&data).is_ok_and(is_compile_error) {
+		return Err(Error::new(ErrorKind::InvalidData, "Invalid compile error"));
+	}
+
+	s.set_position(end as u64);
+
+	Ok(Str {
+		range: start..end,
+		data,
+	})
+}
+
+fn is_code_valid(mut code: &[u8], encoding: Encoding, version: u8) -> bool {
+	while !code.is_empty() {
+		match Inst::decode(code, encoding, version) {
+			Ok(inst) => code = &code[inst.op().size()..],
+			Err(_) => return false,
+		}
+	}
+
+	true
+}
+
+fn find_encoding(data: &[u8], version: u8, function_list: &[Function]) -> Encoding {
+	Encoding::LIST
+		.into_iter()
+		.find(|&encoding| {
+			function_list
+				.iter()
+				.all(|func| is_code_valid(&data[func.code()], encoding, version))
+		})
+		.unwrap_or_default()
+}
+
+fn parse_function_list(
+	format: Format,
+	s: &mut Stream,
+	list: &mut List<Function>,
+) -> Result<(), ParseError> {
+	let start = position_of(s);
+	let len = in_stage(s, Stage::FunctionList, parse_any_size)?;
+	let mut temp = Vec::with_capacity(len.min(remaining_of(s)));
+	let mut end = position_of(s);
+	let mut result = Ok(());
+
+	for i in 0..len {
+		match in_stage(s, Stage::FunctionList, |s| parse_function(format, s)) {
+			Ok(func) => temp.push(func),
+			Err(e) => {
+				result = Err(e.with_function(i));
+
+				break;
+			}
+		}
+
+		end = position_of(s);
+	}
+
+	let offset_list = temp.iter().map(|func| func.position().start).collect();
+
+	*list = List {
+		data: temp.into(),
+		offset_list,
+		range: start..end,
+	};
+
+	result
+}
+
+// Anything that fails after the string list is returned alongside the partial module
+fn parse_module(s: &mut Stream) -> Result<(Module, Option<ParseError>), ParseError> {
+	let mut format = in_stage(s, Stage::Header, parse_format)?;
+
+	if format.version == 0 {
+		let compile_error = in_stage(s, Stage::Header, parse_compile_error)?;
+
+		return Ok((Module::with_error(format, compile_error), None));
+	}
+
+	let string_list = in_stage(s, Stage::StringList, |s| parse_list_of(s, parse_string))?;
+	let userdata_list = in_stage(s, Stage::UserDataList, |s| parse_userdata_list(format, s))?;
+	let mut function_list = List::default();
+
+	let result = parse_function_list(format, s, &mut function_list)
+		.and_then(|()| in_stage(s, Stage::EntryPoint, parse_any_size));
+
+	format.encoding = find_encoding(s.get_ref(), format.version, &function_list.data);
+
+	let (entry_point, error) = match result {
+		Ok(entry_point) => (Some(entry_point), None),
+		Err(e) => (None, Some(e)),
+	};
+
+	let module = Module::new(
+		format,
+		function_list,
+		string_list,
+		userdata_list,
+		entry_point,
+	);
+
+	Ok((module, error))
+}
+
+pub fn parse_bytes(data: &[u8]) -> Result<Module, ParseError> {
+	match salvage_bytes(data)? {
+		(module, None) => Ok(module),
+		(_, Some(e)) => Err(e),
+	}
+}
+
+pub fn salvage_bytes(data: &[u8]) -> Result<(Module, Option<ParseError>), ParseError> {
+	let mut cursor = Cursor::new(data);
 
-	Ok(Module::new(function_list, string_list, entry_point))
+	parse_module(&mut cursor)
 }
 
-pub fn parse(view: &BinaryView) -> Result<Module, ()> {
-	let buffer = view
-		.read_buffer(0, view.len())
-		.expect("Failed to read buffer");
+#[cfg(test)]
+mod test {
+	use super::{parse_bytes, salvage_bytes, Stage};
+	use crate::file::sample::hello;
+
+	#[test]
+	fn parse_each_version() {
+		for version in [3, 4, 6] {
+			let data = hello(version).build();
+			let module = parse_bytes(&data).unwrap();
+
+			assert_eq!(module.format().version, version);
+			assert_eq!(module.function_list().data.len(), 2);
+			assert_eq!(module.start_id(), Some(1));
+			assert_eq!(&*module.string_at(1).unwrap().data, b"print");
+			assert!(module.is_complete());
+		}
+	}
+
+	#[test]
+	fn parse_userdata_list() {
+		let mut sample = hello(6);
 
-	let mut cursor = Cursor::new(buffer.get_data());
+		sample.type_version = 3;
+		sample.userdata_list = vec![(1, 4), (2, 2)];
 
-	parse_module(&mut cursor).map_err(drop)
+		let module = parse_bytes(&sample.build()).unwrap();
+
+		assert_eq!(module.userdata_list().data.len(), 2);
+		assert_eq!(&*module.userdata_name(0).unwrap().data, b"x");
+		assert_eq!(&*module.userdata_name(1).unwrap().data, b"hello");
+		assert!(module.is_complete());
+	}
+
+	#[test]
+	fn parse_long_any_size() {
+		let mut data = vec![6, 1];
+
+		data.extend_from_slice(&[0x80; 16]);
+		data.push(0);
+
+		let error = parse_bytes(&data).err().unwrap();
+
+		assert_eq!(error.stage(), Stage::StringList);
+	}
+
+	#[test]
+	fn parse_truncated() {
+		let data = hello(6).build();
+		let data = &data[..data.len() - 20];
+
+		let error = parse_bytes(data).err().unwrap();
+
+		assert_eq!(error.stage(), Stage::FunctionList);
+
+		let (module, error) = salvage_bytes(data).unwrap();
+
+		assert!(error.is_some());
+		assert!(!module.is_complete());
+		assert_eq!(module.function_list().data.len(), 1);
+	}
+
+	#[test]
+	fn parse_compile_error() {
+		let data = b"\0:1: Incomplete statement: expected assignment or a function call";
+		let module = parse_bytes(data).unwrap();
+
+		assert_eq!(module.format().version, 0);
+		assert_eq!(&*module.compile_error().unwrap().data, &data[1..]);
+
+		for data in [
+			&b"\0\0\x01\x02"[..],
+			b"\0plain text",
+			b"\0:: no line",
+			b"\0:12 no colon",
+		] {
+			let error = parse_bytes(data).err().unwrap();
+
+			assert_eq!(error.stage(), Stage::Header);
+		}
+	}
+
+	#[test]
+	fn parse_bad_version() {
+		let mut data = hello(6).build();
+
+		data[0] = 7;
+
+		assert_eq!(parse_bytes(&data).err().unwrap().stage(), Stage::Header);
+	}
 }
@@ -0,0 +1,61 @@
+use binaryninja::binaryview::{BinaryView, BinaryViewBase, BinaryViewExt};
+use log::{debug, warn};
+
+use super::{data::Module, snapshot};
+
+const MODULE_KEY: &str = "luau.module";
+const SNAPSHOT_KEY: &str = "luau.snapshot";
+const BASE_KEY: &str = "luau.base";
+
+// The version bytes the raw view starts with, which a snapshot must agree with
+fn read_header(view: &BinaryView) -> (u8, u8) {
+	let header = view.read_vec(0, 2);
+	let version = header.first().copied().unwrap_or_default();
+	let type_version = match header.get(1) {
+		Some(&type_version) if version >= 4 => type_version,
+		_ => 0,
+	};
+
+	(version, type_version)
+}
+
+// Stored on the raw view, as it is the only one available when a database reopens.
+// The bytes are kept alongside the module, since every range in it points into them
+pub fn store(view: &BinaryView, base: u64, data: &[u8], module: &Module) {
+	view.store_metadata(MODULE_KEY, data, true);
+	view.store_metadata(SNAPSHOT_KEY, snapshot::encode(module).as_slice(), true);
+	view.store_metadata(BASE_KEY, base, true);
+}
+
+// Saved functions sit at the addresses the module was first mapped at
+pub fn load_base(view: &BinaryView) -> Option<u64> {
+	view.query_metadata(BASE_KEY)?.get_unsigned_integer().ok()
+}
+
+// Anything missing, from an older snapshot layout or for other bytes is left to the
+// caller to parse and store again
+pub fn load(view: &BinaryView) -> Option<(Box<[u8]>, Module)> {
+	let data = view.query_metadata(MODULE_KEY)?.get_raw().ok()?;
+	let blob = view.query_metadata(SNAPSHOT_KEY)?.get_raw().ok()?;
+
+	let module = match snapshot::decode(&blob) {
+		Ok(module) => module,
+		Err(e) => {
+			warn!("Ignored saved Luau module, {e}");
+
+			return None;
+		}
+	};
+
+	let format = module.format();
+
+	let header = (format.version, format.type_version);
+
+	if data.len() != view.len() || read_header(view) != header {
+		debug!("Ignored saved Luau module, it does not match the file");
+
+		return None;
+	}
+
+	Some((data.into(), module))
+}
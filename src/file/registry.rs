@@ -0,0 +1,131 @@
+use std::{
+	collections::hash_map::RandomState,
+	hash::{BuildHasher, Hasher},
+	ops::Range,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc, RwLock,
+	},
+};
+
+use once_cell::sync::Lazy;
+
+use super::data::Module;
+
+// Every view maps its module at a base of its own, leaving the low 32 bits for offsets
+const BASE_SHIFT: u32 = 32;
+
+pub struct Entry {
+	base: u64,
+	data: Box<[u8]>,
+	module: Module,
+}
+
+impl Entry {
+	pub fn base(&self) -> u64 {
+		self.base
+	}
+
+	pub fn data(&self) -> &[u8] {
+		&self.data
+	}
+
+	pub fn module(&self) -> &Module {
+		&self.module
+	}
+
+	pub fn range(&self) -> Range<u64> {
+		self.base..self.base + self.data.len() as u64
+	}
+
+	// Module ranges are file offsets, while the core works with view addresses
+	pub fn offset_of(&self, addr: u64) -> u64 {
+		addr.wrapping_sub(self.base)
+	}
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+static ENTRY_LIST: Lazy<RwLock<Vec<Arc<Entry>>>> = Lazy::new(RwLock::default);
+
+// Bases are saved with the database, so they must not repeat across sessions either
+pub fn new_base() -> u64 {
+	loop {
+		let mut hasher = RandomState::new().build_hasher();
+
+		hasher.write_u64(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+
+		let slot = u64::from(hasher.finish() as u16) + 1;
+		let base = slot << BASE_SHIFT;
+
+		if find(base).is_none() {
+			return base;
+		}
+	}
+}
+
+pub fn insert(base: u64, data: Box<[u8]>, module: Module) -> Arc<Entry> {
+	let entry = Arc::new(Entry { base, data, module });
+
+	ENTRY_LIST.write().unwrap().push(entry.clone());
+
+	entry
+}
+
+pub fn remove(entry: &Arc<Entry>) {
+	ENTRY_LIST
+		.write()
+		.unwrap()
+		.retain(|v| !Arc::ptr_eq(v, entry));
+}
+
+// A database opened twice shares its base, and both copies hold the same module
+pub fn find(addr: u64) -> Option<Arc<Entry>> {
+	ENTRY_LIST
+		.read()
+		.unwrap()
+		.iter()
+		.rfind(|v| v.range().contains(&addr))
+		.cloned()
+}
+
+#[cfg(test)]
+mod test {
+	use super::{find, insert, new_base, remove};
+	use crate::file::{parser::parse_bytes, sample::hello, writer::Writer};
+
+	#[test]
+	fn find_by_address() {
+		let a = hello(6).build();
+		let module = parse_bytes(&a).unwrap();
+		let mut writer = Writer::new(&a, &module);
+
+		// Same code at the same offsets, different constants
+		writer.replace_number(1, 3, 42.0).unwrap();
+
+		let b = writer.write().unwrap();
+
+		let (base_a, base_b) = (new_base(), new_base());
+		let entry_a = insert(base_a, a.clone().into(), module);
+		let entry_b = insert(base_b, b.clone().into(), parse_bytes(&b).unwrap());
+
+		// The same offset in each view resolves to its own module
+		let offset = a.len() as u64 - 1;
+
+		assert_ne!(base_a, base_b);
+		assert_eq!(find(base_a + offset).unwrap().data(), &a[..]);
+		assert_eq!(find(base_b + offset).unwrap().data(), &b[..]);
+		assert_eq!(
+			find(base_a + offset).unwrap().offset_of(base_a + offset),
+			offset
+		);
+		assert!(find(base_a + a.len() as u64).is_none());
+		assert!(find(offset).is_none());
+
+		remove(&entry_a);
+
+		assert!(find(base_a).is_none());
+		assert!(find(base_b).is_some());
+
+		remove(&entry_b);
+	}
+}
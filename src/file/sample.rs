@@ -0,0 +1,227 @@
+// Hand made modules for tests, laid out the way the compiler writes them
+
+pub fn inst(op: u8, a: u8, b: u8, c: u8) -> u32 {
+	u32::from_le_bytes([op, a, b, c])
+}
+
+pub fn inst_d(op: u8, a: u8, d: i16) -> u32 {
+	let [low, high] = d.to_le_bytes();
+
+	u32::from_le_bytes([op, a, low, high])
+}
+
+// Imports hold up to three constant ids, the first one in the highest bits
+pub fn import(id_list: &[u32]) -> u32 {
+	let len = id_list.len() as u32;
+
+	id_list
+		.iter()
+		.zip([20, 10, 0])
+		.fold(len << 30, |data, (id, shift)| data | id << shift)
+}
+
+pub enum Constant {
+	String(usize),
+	Number(f64),
+	Import(u32),
+}
+
+#[derive(Default)]
+pub struct SampleFunction {
+	pub max_stack_size: u8,
+	pub num_param: u8,
+	pub num_upval: u8,
+	pub is_vararg: bool,
+	pub code: Vec<u32>,
+	pub constant_list: Vec<Constant>,
+	pub reference_list: Vec<usize>,
+	pub line_defined: usize,
+	pub debug_name: usize,
+	pub has_line_info: bool,
+	pub has_var_info: bool,
+	pub local_list: Vec<(usize, usize, usize, u8)>,
+	pub upvalue_list: Vec<usize>,
+}
+
+pub struct Sample {
+	pub version: u8,
+	pub type_version: u8,
+	pub string_list: Vec<&'static [u8]>,
+	pub userdata_list: Vec<(u8, usize)>,
+	pub function_list: Vec<SampleFunction>,
+	pub start_id: usize,
+}
+
+fn write_any_size(b: &mut Vec<u8>, mut value: usize) {
+	while value >= 0x80 {
+		b.push((value & 0x7F) as u8 | 0x80);
+
+		value >>= 7;
+	}
+
+	b.push(value as u8);
+}
+
+impl Sample {
+	fn write_constant(b: &mut Vec<u8>, constant: &Constant) {
+		match *constant {
+			Constant::String(index) => {
+				b.push(3);
+				write_any_size(b, index);
+			}
+			Constant::Number(value) => {
+				b.push(2);
+				b.extend_from_slice(&value.to_bits().to_le_bytes());
+			}
+			Constant::Import(data) => {
+				b.push(4);
+				b.extend_from_slice(&data.to_le_bytes());
+			}
+		}
+	}
+
+	fn write_function(&self, b: &mut Vec<u8>, func: &SampleFunction) {
+		b.extend_from_slice(&[
+			func.max_stack_size,
+			func.num_param,
+			func.num_upval,
+			func.is_vararg.into(),
+		]);
+
+		if self.version >= 4 {
+			b.extend_from_slice(&[0, 0]);
+		}
+
+		write_any_size(b, func.code.len());
+
+		for inst in &func.code {
+			b.extend_from_slice(&inst.to_le_bytes());
+		}
+
+		write_any_size(b, func.constant_list.len());
+
+		for constant in &func.constant_list {
+			Self::write_constant(b, constant);
+		}
+
+		write_any_size(b, func.reference_list.len());
+
+		for &index in &func.reference_list {
+			write_any_size(b, index);
+		}
+
+		write_any_size(b, func.line_defined);
+		write_any_size(b, func.debug_name);
+
+		// Every instruction is on line one, with a gap of one instruction
+		b.push(func.has_line_info.into());
+
+		if func.has_line_info {
+			b.push(0);
+			b.extend(func.code.iter().map(|_| 0));
+			b.extend_from_slice(&1_u32.to_le_bytes());
+			b.extend(func.code.iter().skip(1).flat_map(|_| 0_u32.to_le_bytes()));
+		}
+
+		b.push(func.has_var_info.into());
+
+		if func.has_var_info {
+			write_any_size(b, func.local_list.len());
+
+			for &(name, start_pc, end_pc, register) in &func.local_list {
+				write_any_size(b, name);
+				write_any_size(b, start_pc);
+				write_any_size(b, end_pc);
+				b.push(register);
+			}
+
+			write_any_size(b, func.upvalue_list.len());
+
+			for &name in &func.upvalue_list {
+				write_any_size(b, name);
+			}
+		}
+	}
+
+	pub fn build(&self) -> Vec<u8> {
+		let mut b = vec![self.version];
+
+		if self.version >= 4 {
+			b.push(self.type_version);
+		}
+
+		write_any_size(&mut b, self.string_list.len());
+
+		for data in &self.string_list {
+			write_any_size(&mut b, data.len());
+			b.extend_from_slice(data);
+		}
+
+		if self.type_version == 3 {
+			for &(index, name) in &self.userdata_list {
+				b.push(index);
+				write_any_size(&mut b, name);
+			}
+
+			b.push(0);
+		}
+
+		write_any_size(&mut b, self.function_list.len());
+
+		for func in &self.function_list {
+			self.write_function(&mut b, func);
+		}
+
+		write_any_size(&mut b, self.start_id);
+
+		b
+	}
+}
+
+// `print("hello")` followed by a closure capturing `x`
+pub fn hello(version: u8) -> Sample {
+	let child = SampleFunction {
+		max_stack_size: 1,
+		num_upval: 1,
+		code: vec![inst(9, 0, 0, 0), inst(22, 0, 2, 0)],
+		line_defined: 2,
+		has_line_info: true,
+		has_var_info: true,
+		upvalue_list: vec![4],
+		..SampleFunction::default()
+	};
+
+	let main = SampleFunction {
+		max_stack_size: 3,
+		is_vararg: true,
+		code: vec![
+			inst(12, 0, 1, 0),
+			import(&[0]),
+			inst(5, 1, 2, 0),
+			inst(21, 0, 2, 1),
+			inst_d(19, 2, 0),
+			inst(22, 0, 1, 0),
+		],
+		constant_list: vec![
+			Constant::String(1),
+			Constant::Import(import(&[0])),
+			Constant::String(2),
+			Constant::Number(1.5),
+		],
+		reference_list: vec![0],
+		debug_name: 3,
+		has_line_info: true,
+		has_var_info: true,
+		local_list: vec![(4, 4, 6, 2)],
+		..SampleFunction::default()
+	};
+
+	Sample {
+		version,
+		type_version: if version >= 4 { 1 } else { 0 },
+		string_list: vec![b"print", b"hello", b"main", b"x"],
+		userdata_list: Vec::new(),
+		function_list: vec![child, main],
+		start_id: 1,
+	}
+}
@@ -0,0 +1,429 @@
+use std::{
+	collections::HashMap,
+	io::{Cursor, Error, ErrorKind},
+	ops::Range,
+};
+
+use crate::decoder::encoding::Encoding;
+
+use super::{
+	data::{
+		DebugInfo, Format, Function, LineInfo, List, Local, MetaData, Module, Str, TypeInfo,
+		TypeKind, TypeTag, TypedLocal, UserDataType, Value,
+	},
+	parser::{
+		parse_any_size, parse_constant, parse_sized_data, parse_type_tag, parse_u32, parse_u8,
+		PResult, Stream, TYPE_OPTIONAL_BIT, TYPE_TAGGED_USERDATA_BASE,
+	},
+	writer::{write_any_size, write_constant, write_sized_data, write_u32, write_u8, Buffer},
+};
+
+// Bumped whenever the layout below changes, so older snapshots are parsed again
+const SNAPSHOT_VERSION: u8 = 1;
+
+// A parsed module, including what was inferred about it, in a compact binary form.
+// Numbers and constants are laid out as in bytecode, everything else is our own
+fn write_range(b: &mut Buffer, range: &Range<usize>) {
+	write_any_size(b, range.start);
+	write_any_size(b, range.end);
+}
+
+fn write_list<T, W>(b: &mut Buffer, list: &List<T>, write: W)
+where
+	W: Fn(&mut Buffer, &T),
+{
+	write_range(b, &list.range);
+	write_any_size(b, list.data.len());
+
+	for (value, &offset) in list.data.iter().zip(list.offset_list.iter()) {
+		write_any_size(b, offset);
+		write(b, value);
+	}
+}
+
+fn write_option<T, W>(b: &mut Buffer, value: Option<&T>, write: W)
+where
+	W: FnOnce(&mut Buffer, &T),
+{
+	write_u8(b, value.is_some().into());
+
+	if let Some(value) = value {
+		write(b, value);
+	}
+}
+
+fn write_str(b: &mut Buffer, value: &Str) {
+	write_range(b, &value.range);
+	write_sized_data(b, &value.data);
+}
+
+fn write_type_tag(b: &mut Buffer, tag: &TypeTag) {
+	let kind = match tag.kind {
+		TypeKind::Nil => 0,
+		TypeKind::Boolean => 1,
+		TypeKind::Number => 2,
+		TypeKind::String => 3,
+		TypeKind::Table => 4,
+		TypeKind::Function => 5,
+		TypeKind::Thread => 6,
+		TypeKind::UserData => 7,
+		TypeKind::Vector => 8,
+		TypeKind::Buffer => 9,
+		TypeKind::Any => 15,
+		TypeKind::Tagged(v) => TYPE_TAGGED_USERDATA_BASE + v,
+		TypeKind::Unknown(v) => v,
+	};
+
+	let optional = if tag.is_optional {
+		TYPE_OPTIONAL_BIT
+	} else {
+		0
+	};
+
+	write_u8(b, kind | optional);
+}
+
+fn write_tag_list(b: &mut Buffer, list: &[TypeTag]) {
+	write_any_size(b, list.len());
+
+	for tag in list {
+		write_type_tag(b, tag);
+	}
+}
+
+fn write_type_info(b: &mut Buffer, info: &TypeInfo) {
+	write_tag_list(b, &info.param_list);
+	write_tag_list(b, &info.upvalue_list);
+	write_any_size(b, info.local_list.len());
+
+	for local in info.local_list.iter() {
+		write_type_tag(b, &local.tag);
+		write_u8(b, local.register);
+		write_any_size(b, local.start_pc);
+		write_any_size(b, local.end_pc);
+	}
+}
+
+fn write_meta_data(b: &mut Buffer, meta_data: &MetaData) {
+	write_u8(b, meta_data.max_stack_size);
+	write_u8(b, meta_data.num_param);
+	write_u8(b, meta_data.num_upval);
+	write_u8(b, meta_data.is_vararg.into());
+	write_u8(b, meta_data.flags);
+	write_range(b, &meta_data.type_range);
+	write_option(b, meta_data.type_info.as_ref(), write_type_info);
+	write_any_size(b, meta_data.line_defined);
+}
+
+fn write_line_info(b: &mut Buffer, info: &LineInfo) {
+	write_u8(b, info.gap_log2);
+	write_sized_data(b, &info.offset_list);
+	write_any_size(b, info.line_list.len());
+
+	for &line in info.line_list.iter() {
+		write_u32(b, line as u32);
+	}
+}
+
+fn write_debug_info(b: &mut Buffer, info: &DebugInfo) {
+	write_option(b, info.line_info.as_ref(), write_line_info);
+	write_any_size(b, info.local_list.len());
+
+	for local in info.local_list.iter() {
+		write_any_size(b, local.name);
+		write_any_size(b, local.start_pc);
+		write_any_size(b, local.end_pc);
+		write_u8(b, local.register);
+	}
+
+	write_any_size(b, info.upvalue_list.len());
+
+	for &name in info.upvalue_list.iter() {
+		write_any_size(b, name);
+	}
+}
+
+fn write_function(b: &mut Buffer, func: &Function) {
+	write_range(b, &func.position());
+	write_any_size(b, func.name());
+	write_range(b, &func.code());
+	write_list(b, func.constant_list(), write_constant);
+	write_list(b, func.reference_list(), |b, v| write_any_size(b, *v));
+	write_meta_data(b, func.meta_data());
+	write_debug_info(b, func.debug_info());
+}
+
+pub fn encode(module: &Module) -> Vec<u8> {
+	let format = module.format();
+	let mut b = Buffer::new();
+
+	write_u8(&mut b, SNAPSHOT_VERSION);
+	write_u8(&mut b, format.version);
+	write_u8(&mut b, format.type_version);
+	write_u8(&mut b, format.encoding.id());
+
+	write_option(&mut b, module.compile_error(), write_str);
+	write_list(&mut b, module.string_list(), write_str);
+	write_list(&mut b, module.userdata_list(), |b, v| {
+		write_u8(b, v.index);
+		write_any_size(b, v.name);
+	});
+	write_list(&mut b, module.function_list(), write_function);
+	write_option(&mut b, module.start_id().as_ref(), |b, v| {
+		write_any_size(b, *v)
+	});
+
+	let mut name_list: Vec<_> = module.inferred_name_map().iter().collect();
+
+	name_list.sort_unstable();
+	write_any_size(&mut b, name_list.len());
+
+	for (&index, name) in name_list {
+		write_any_size(&mut b, index);
+		write_sized_data(&mut b, name.as_bytes());
+	}
+
+	b
+}
+
+fn parse_range(s: &mut Stream) -> PResult<Range<usize>> {
+	let start = parse_any_size(s)?;
+	let end = parse_any_size(s)?;
+
+	Ok(start..end)
+}
+
+fn parse_bytes(s: &mut Stream) -> PResult<Box<[u8]>> {
+	let range = parse_sized_data(s)?;
+
+	Ok(s.get_ref()[range].into())
+}
+
+fn parse_vec<P, O>(s: &mut Stream, parse: P) -> PResult<Vec<O>>
+where
+	P: Fn(&mut Stream) -> PResult<O>,
+{
+	let len = parse_any_size(s)?;
+
+	(0..len).map(|_| parse(s)).collect()
+}
+
+fn parse_list<P, O>(s: &mut Stream, parse: P) -> PResult<List<O>>
+where
+	P: Fn(&mut Stream) -> PResult<O>,
+{
+	let range = parse_range(s)?;
+	let entry_list = parse_vec(s, |s| Ok((parse_any_size(s)?, parse(s)?)))?;
+	let (offset_list, data): (Vec<_>, Vec<_>) = entry_list.into_iter().unzip();
+
+	Ok(List {
+		data: data.into(),
+		offset_list: offset_list.into(),
+		range,
+	})
+}
+
+fn parse_option<P, O>(s: &mut Stream, parse: P) -> PResult<Option<O>>
+where
+	P: FnOnce(&mut Stream) -> PResult<O>,
+{
+	if parse_u8(s)? == 0 {
+		Ok(None)
+	} else {
+		parse(s).map(Some)
+	}
+}
+
+fn parse_str(s: &mut Stream) -> PResult<Str> {
+	let range = parse_range(s)?;
+	let data = parse_bytes(s)?;
+
+	Ok(Str { range, data })
+}
+
+fn parse_type_info(s: &mut Stream) -> PResult<TypeInfo> {
+	let param_list = parse_vec(s, parse_type_tag)?.into();
+	let upvalue_list = parse_vec(s, parse_type_tag)?.into();
+	let local_list = parse_vec(s, |s| {
+		Ok(TypedLocal {
+			tag: parse_type_tag(s)?,
+			register: parse_u8(s)?,
+			start_pc: parse_any_size(s)?,
+			end_pc: parse_any_size(s)?,
+		})
+	})?;
+
+	Ok(TypeInfo {
+		param_list,
+		upvalue_list,
+		local_list: local_list.into(),
+	})
+}
+
+fn parse_meta_data(s: &mut Stream) -> PResult<MetaData> {
+	Ok(MetaData {
+		max_stack_size: parse_u8(s)?,
+		num_param: parse_u8(s)?,
+		num_upval: parse_u8(s)?,
+		is_vararg: parse_u8(s)? != 0,
+		flags: parse_u8(s)?,
+		type_range: parse_range(s)?,
+		type_info: parse_option(s, parse_type_info)?,
+		line_defined: parse_any_size(s)?,
+	})
+}
+
+fn parse_line_info(s: &mut Stream) -> PResult<LineInfo> {
+	let gap_log2 = parse_u8(s)?;
+	let offset_list = parse_bytes(s)?;
+	let line_list = parse_vec(s, |s| Ok(parse_u32(s)? as i32))?;
+
+	Ok(LineInfo {
+		gap_log2,
+		offset_list,
+		line_list: line_list.into(),
+	})
+}
+
+fn parse_debug_info(s: &mut Stream) -> PResult<DebugInfo> {
+	let line_info = parse_option(s, parse_line_info)?;
+	let local_list = parse_vec(s, |s| {
+		Ok(Local {
+			name: parse_any_size(s)?,
+			start_pc: parse_any_size(s)?,
+			end_pc: parse_any_size(s)?,
+			register: parse_u8(s)?,
+		})
+	})?;
+	let upvalue_list = parse_vec(s, parse_any_size)?;
+
+	Ok(DebugInfo {
+		line_info,
+		local_list: local_list.into(),
+		upvalue_list: upvalue_list.into(),
+	})
+}
+
+fn parse_function(s: &mut Stream) -> PResult<Function> {
+	let position = parse_range(s)?;
+	let name = parse_any_size(s)?;
+	let code = parse_range(s)?;
+	let constant_list = parse_list::<_, Value>(s, parse_constant)?;
+	let reference_list = parse_list(s, parse_any_size)?;
+	let meta_data = parse_meta_data(s)?;
+	let debug_info = parse_debug_info(s)?;
+
+	Ok(Function::new(
+		position,
+		name,
+		code,
+		constant_list,
+		reference_list,
+		meta_data,
+		debug_info,
+	))
+}
+
+fn parse_module(s: &mut Stream) -> PResult<Module> {
+	if parse_u8(s)? != SNAPSHOT_VERSION {
+		return Err(Error::new(ErrorKind::InvalidData, "Outdated snapshot"));
+	}
+
+	let version = parse_u8(s)?;
+	let type_version = parse_u8(s)?;
+	let encoding = Encoding::from_id(parse_u8(s)?)
+		.ok_or_else(|| Error::new(ErrorKind::InvalidData, "Invalid encoding"))?;
+
+	let format = Format {
+		version,
+		type_version,
+		encoding,
+	};
+
+	if let Some(compile_error) = parse_option(s, parse_str)? {
+		return Ok(Module::with_error(format, compile_error));
+	}
+
+	let string_list = parse_list(s, parse_str)?;
+	let userdata_list = parse_list(s, |s| {
+		Ok(UserDataType {
+			index: parse_u8(s)?,
+			name: parse_any_size(s)?,
+		})
+	})?;
+	let function_list = parse_list(s, parse_function)?;
+	let start_id = parse_option(s, parse_any_size)?;
+
+	let name_list = parse_vec(s, |s| {
+		let index = parse_any_size(s)?;
+		let name = String::from_utf8_lossy(&parse_bytes(s)?).into_owned();
+
+		Ok((index, name))
+	})?;
+
+	let mut module = Module::new(format, function_list, string_list, userdata_list, start_id);
+
+	module.set_inferred_names(name_list.into_iter().collect::<HashMap<_, _>>());
+
+	Ok(module)
+}
+
+pub fn decode(data: &[u8]) -> PResult<Module> {
+	let mut cursor = Cursor::new(data);
+
+	parse_module(&mut cursor)
+}
+
+#[cfg(test)]
+mod test {
+	use super::{decode, encode};
+	use crate::{
+		decoder::encoding::Encoding,
+		file::{naming::infer_names, parser::parse_bytes, sample::hello},
+	};
+
+	#[test]
+	fn snapshot_round_trip() {
+		let mut sample = hello(6);
+
+		sample.type_version = 3;
+		sample.userdata_list = vec![(1, 4)];
+
+		let data = sample.build();
+		let mut module = parse_bytes(&data).unwrap();
+
+		module.set_inferred_names(infer_names(&data, &module));
+		module.set_encoding(Encoding::LIST[1]);
+
+		let snapshot = encode(&module);
+		let loaded = decode(&snapshot).unwrap();
+
+		assert_eq!(encode(&loaded), snapshot);
+		assert!(loaded.format().encoding == Encoding::LIST[1]);
+		assert_eq!(loaded.function_list().data.len(), 2);
+		assert_eq!(loaded.function_name(0), module.function_name(0));
+		assert_eq!(loaded.qualified_name(0), module.qualified_name(0));
+		assert_eq!(&*loaded.userdata_name(0).unwrap().data, b"x");
+		assert!(loaded.is_complete());
+	}
+
+	#[test]
+	fn snapshot_compile_error() {
+		let data = b"\0:1: Incomplete statement: expected assignment or a function call";
+		let module = parse_bytes(data).unwrap();
+		let loaded = decode(&encode(&module)).unwrap();
+
+		assert_eq!(&*loaded.compile_error().unwrap().data, &data[1..]);
+	}
+
+	#[test]
+	fn snapshot_outdated() {
+		let module = parse_bytes(&hello(6).build()).unwrap();
+		let mut snapshot = encode(&module);
+
+		snapshot[0] = 0;
+
+		assert!(decode(&snapshot).is_err());
+		assert!(decode(&snapshot[..snapshot.len() / 2]).is_err());
+	}
+}
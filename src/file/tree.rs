@@ -0,0 +1,67 @@
+use super::data::Function;
+
+#[derive(Default)]
+pub struct ProtoTree {
+	parent_list: Box<[Option<usize>]>,
+	child_list: Box<[Box<[usize]>]>,
+}
+
+impl ProtoTree {
+	// Each prototype belongs to the first function that references it
+	pub fn new(function_list: &[Function]) -> Self {
+		let len = function_list.len();
+		let mut parent_list = vec![None; len];
+		let mut child_list = Vec::with_capacity(len);
+
+		for (parent, func) in function_list.iter().enumerate() {
+			let mut temp = Vec::new();
+
+			for &child in func.reference_list().data.iter() {
+				if child >= len || child == parent || parent_list[child].is_some() {
+					continue;
+				}
+
+				parent_list[child] = Some(parent);
+				temp.push(child);
+			}
+
+			child_list.push(temp.into());
+		}
+
+		Self {
+			parent_list: parent_list.into(),
+			child_list: child_list.into(),
+		}
+	}
+
+	pub fn parent_of(&self, index: usize) -> Option<usize> {
+		self.parent_list.get(index).copied().flatten()
+	}
+
+	pub fn children_of(&self, index: usize) -> &[usize] {
+		self.child_list.get(index).map_or(&[], |v| v)
+	}
+
+	pub fn root_list(&self) -> impl Iterator<Item = usize> + '_ {
+		self.parent_list
+			.iter()
+			.enumerate()
+			.filter_map(|(i, v)| v.is_none().then_some(i))
+	}
+
+	// Hand made bytecode can reference prototypes in a cycle, so the walk is bounded
+	pub fn path_of(&self, index: usize) -> Vec<usize> {
+		let mut path = vec![index];
+
+		while let Some(parent) = self.parent_of(*path.last().unwrap()) {
+			if path.len() > self.parent_list.len() || path.contains(&parent) {
+				break;
+			}
+
+			path.push(parent);
+		}
+
+		path.reverse();
+		path
+	}
+}
@@ -0,0 +1,229 @@
+use std::fmt::{Display, Formatter, Result as FResult};
+
+use crate::decoder::import::Import;
+
+use super::data::{Function, Module, Value};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+	String,
+	Closure,
+	Reference,
+	Import,
+	TableKey,
+	StartId,
+	Opcode,
+	Register,
+	UpValue,
+	Constant,
+	Prototype,
+	Jump,
+}
+
+impl Kind {
+	pub const fn name(self) -> &'static str {
+		match self {
+			Self::String => "string",
+			Self::Closure => "closure",
+			Self::Reference => "child function",
+			Self::Import => "import",
+			Self::TableKey => "table key",
+			Self::StartId => "start function",
+			Self::Opcode => "opcode",
+			Self::Register => "register",
+			Self::UpValue => "upvalue",
+			Self::Constant => "constant",
+			Self::Prototype => "closure prototype",
+			Self::Jump => "jump target",
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct Diagnostic {
+	pub kind: Kind,
+	pub function: Option<usize>,
+	pub offset: usize,
+	pub index: i64,
+	pub len: usize,
+}
+
+impl Display for Diagnostic {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FResult {
+		let name = self.kind.name();
+
+		match self.kind {
+			Kind::Opcode => write!(f, "invalid {name} {:#04x}", self.index)?,
+			Kind::Jump if (0..self.len as i64).contains(&self.index) => {
+				write!(f, "{name} {} is not an instruction boundary", self.index)?;
+			}
+			_ => write!(
+				f,
+				"{name} index {} is out of range, expected less than {}",
+				self.index, self.len
+			)?,
+		}
+
+		if let Some(function) = self.function {
+			write!(f, " in function {function}")?;
+		}
+
+		write!(f, " at offset {:#x}", self.offset)
+	}
+}
+
+struct Validator<'a> {
+	module: &'a Module,
+	list: Vec<Diagnostic>,
+}
+
+impl<'a> Validator<'a> {
+	fn check(
+		&mut self,
+		kind: Kind,
+		function: Option<usize>,
+		offset: usize,
+		index: usize,
+		len: usize,
+	) {
+		if index >= len {
+			self.list.push(Diagnostic {
+				kind,
+				function,
+				offset,
+				index: index as i64,
+				len,
+			});
+		}
+	}
+
+	// String references are one based, with zero meaning no string
+	fn check_string(&mut self, function: usize, offset: usize, index: usize) {
+		let len = self.module.string_list().data.len();
+
+		if index != 0 {
+			self.check(Kind::String, Some(function), offset, index - 1, len);
+		}
+	}
+
+	fn check_constant(&mut self, function: usize, func: &Function, offset: usize, value: &Value) {
+		let num_function = self.module.function_list().data.len();
+		let num_constant = func.constant_list().data.len();
+
+		match value {
+			Value::String(index) => self.check_string(function, offset, *index),
+			Value::Closure(index) => {
+				self.check(Kind::Closure, Some(function), offset, *index, num_function);
+			}
+			Value::Import(data) => {
+				for index in Import::from(*data) {
+					self.check(Kind::Import, Some(function), offset, index, num_constant);
+				}
+			}
+			Value::Table(key_list) => {
+				for &index in key_list.iter() {
+					self.check(Kind::TableKey, Some(function), offset, index, num_constant);
+				}
+			}
+			_ => {}
+		}
+	}
+
+	fn check_function(&mut self, function: usize, func: &Function) {
+		let num_function = self.module.function_list().data.len();
+
+		let constant_list = func.constant_list();
+
+		for (value, &offset) in constant_list.data.iter().zip(&constant_list.offset_list) {
+			self.check_constant(function, func, offset, value);
+		}
+
+		let reference_list = func.reference_list();
+
+		for (&index, &offset) in reference_list.data.iter().zip(&reference_list.offset_list) {
+			self.check(Kind::Reference, Some(function), offset, index, num_function);
+		}
+	}
+}
+
+// Collects every cross reference in the module that points out of range
+pub fn validate(module: &Module) -> Vec<Diagnostic> {
+	let mut validator = Validator {
+		module,
+		list: Vec::new(),
+	};
+
+	let function_list = module.function_list();
+
+	for (i, func) in function_list.data.iter().enumerate() {
+		validator.check_function(i, func);
+	}
+
+	if let Some(start_id) = module.start_id() {
+		let offset = function_list.range.end;
+		let len = function_list.data.len();
+
+		validator.check(Kind::StartId, None, offset, start_id, len);
+	}
+
+	validator.list
+}
+
+#[cfg(test)]
+mod test {
+	use super::{validate, Kind};
+	use crate::file::{
+		parser::parse_bytes,
+		sample::{hello, import, Constant},
+	};
+
+	#[test]
+	fn validate_clean() {
+		for version in [3, 4, 6] {
+			let module = parse_bytes(&hello(version).build()).unwrap();
+
+			assert!(validate(&module).is_empty());
+		}
+	}
+
+	#[test]
+	fn validate_import() {
+		let mut sample = hello(6);
+
+		// Only the first id is out of range, the empty slots after it are not checked
+		sample.function_list[1].constant_list[1] = Constant::Import(import(&[9]));
+
+		let module = parse_bytes(&sample.build()).unwrap();
+		let list = validate(&module);
+
+		assert_eq!(list.len(), 1);
+		assert_eq!(list[0].kind, Kind::Import);
+		assert_eq!(list[0].index, 9);
+		assert_eq!(
+			list[0].offset,
+			module.function_list().data[1].constant_list().offset_list[1]
+		);
+	}
+
+	#[test]
+	fn validate_offset() {
+		let mut sample = hello(6);
+
+		sample.function_list[1].constant_list[2] = Constant::String(7);
+		sample.function_list[1].reference_list = vec![0, 5];
+
+		let data = sample.build();
+		let module = parse_bytes(&data).unwrap();
+		let list = validate(&module);
+
+		assert_eq!(list.len(), 2);
+
+		// A string constant is its tag followed by the index
+		assert_eq!(list[0].kind, Kind::String);
+		assert_eq!(data[list[0].offset], 3);
+		assert_eq!(data[list[0].offset + 1], 7);
+
+		assert_eq!(list[1].kind, Kind::Reference);
+		assert_eq!(data[list[1].offset], 5);
+	}
+}
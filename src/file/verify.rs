@@ -0,0 +1,218 @@
+use crate::decoder::{
+	encoding::Encoding,
+	inst::Inst,
+	opcode::{OpType, Opcode},
+	operand::CaptureKind,
+};
+
+use super::{
+	data::{Function, Module},
+	validate::{Diagnostic, Kind},
+};
+
+struct Verifier<'a> {
+	func: &'a Function,
+	function: usize,
+	boundary_list: Vec<bool>,
+	list: Vec<Diagnostic>,
+}
+
+impl<'a> Verifier<'a> {
+	fn report(&mut self, kind: Kind, pc: usize, index: i64, len: usize) {
+		self.list.push(Diagnostic {
+			kind,
+			function: Some(self.function),
+			offset: self.func.code().start + pc * 4,
+			index,
+			len,
+		});
+	}
+
+	fn check(&mut self, kind: Kind, pc: usize, index: i64, len: usize) {
+		if !(0..len as i64).contains(&index) {
+			self.report(kind, pc, index, len);
+		}
+	}
+
+	fn check_jump(&mut self, pc: usize, target: i64) {
+		let is_boundary = usize::try_from(target)
+			.ok()
+			.and_then(|target| self.boundary_list.get(target))
+			.copied()
+			.unwrap_or(false);
+
+		if !is_boundary {
+			self.report(Kind::Jump, pc, target, self.boundary_list.len());
+		}
+	}
+
+	// Targets are worked out on code relative addresses, so a jump before the
+	// start wraps around to a negative pc
+	fn jump_target_of(inst: Inst, pc: usize, offset: i64) -> i64 {
+		let target = inst.location_target(pc as u64 * 4, offset);
+
+		target as i64 / 4
+	}
+
+	fn check_inst(&mut self, inst: Inst, pc: usize) {
+		let op = inst.op();
+		let max_stack_size = self.func.max_stack_size().into();
+		let num_upval = self.func.num_upval().into();
+		let num_constant = self.func.constant_list().data.len();
+		let num_reference = self.func.reference_list().data.len();
+
+		for (name, typ) in op.iter_operands() {
+			let raw = match inst.with_name(name) {
+				Some(raw) => i64::from(raw),
+				None => continue,
+			};
+
+			match typ {
+				OpType::Register => self.check(Kind::Register, pc, raw, max_stack_size),
+				OpType::UpValue => self.check(Kind::UpValue, pc, raw, num_upval),
+				OpType::Constant => self.check(Kind::Constant, pc, raw, num_constant),
+				OpType::Function => self.check(Kind::Prototype, pc, raw, num_reference),
+				OpType::CaptureIndex => match CaptureKind::from_raw(inst.a().into()) {
+					Some(CaptureKind::UpValue) => self.check(Kind::UpValue, pc, raw, num_upval),
+					_ => self.check(Kind::Register, pc, raw, max_stack_size),
+				},
+				OpType::Location | OpType::Skip => {
+					let target = Self::jump_target_of(inst, pc, raw);
+
+					// A zero skip on `load_boolean` is not a jump at all
+					if !(matches!(op, Opcode::LoadBoolean) && raw == 0) {
+						self.check_jump(pc, target);
+					}
+				}
+				_ => {}
+			}
+		}
+	}
+
+	fn verify(&mut self, code: &[u8], encoding: Encoding, version: u8) {
+		let mut inst_list = Vec::new();
+		let mut pc = 0;
+
+		// Find the instruction boundaries first so jumps can be checked in any direction
+		while pc < self.boundary_list.len() {
+			self.boundary_list[pc] = true;
+
+			match Inst::decode(&code[pc * 4..], encoding, version) {
+				Ok(inst) => {
+					inst_list.push((inst, pc));
+
+					pc += inst.op().size() / 4;
+				}
+				Err(_) => {
+					let first = encoding.decode(code[pc * 4]);
+
+					self.report(Kind::Opcode, pc, first.into(), 0);
+
+					pc += 1;
+				}
+			}
+		}
+
+		for (inst, pc) in inst_list {
+			self.check_inst(inst, pc);
+		}
+	}
+}
+
+// Checks every instruction operand against the bounds of its function
+pub fn verify(data: &[u8], module: &Module) -> Vec<Diagnostic> {
+	let format = module.format();
+	let mut list = Vec::new();
+
+	for (function, func) in module.function_list().data.iter().enumerate() {
+		let code = match data.get(func.code()) {
+			Some(code) => code,
+			None => continue,
+		};
+
+		let mut verifier = Verifier {
+			func,
+			function,
+			boundary_list: vec![false; code.len() / 4],
+			list,
+		};
+
+		verifier.verify(code, format.encoding, format.version);
+
+		list = verifier.list;
+	}
+
+	list
+}
+
+#[cfg(test)]
+mod test {
+	use super::verify;
+	use crate::file::{
+		parser::parse_bytes,
+		sample::{hello, inst, inst_d, Sample, SampleFunction},
+	};
+
+	fn verify_code(code: Vec<u32>) -> usize {
+		let main = SampleFunction {
+			max_stack_size: 3,
+			code,
+			..SampleFunction::default()
+		};
+
+		let sample = Sample {
+			version: 6,
+			type_version: 1,
+			string_list: Vec::new(),
+			userdata_list: Vec::new(),
+			function_list: vec![main],
+			start_id: 0,
+		};
+
+		let data = sample.build();
+		let module = parse_bytes(&data).unwrap();
+
+		verify(&data, &module).len()
+	}
+
+	#[test]
+	fn verify_clean() {
+		for version in [3, 4, 6] {
+			let data = hello(version).build();
+			let module = parse_bytes(&data).unwrap();
+
+			assert!(verify(&data, &module).is_empty());
+		}
+	}
+
+	#[test]
+	fn verify_fast_call() {
+		// fast_call2 lands just past the call, whose distance does not count the aux word
+		let code = vec![
+			inst(74, 1, 1, 2),
+			2,
+			inst(6, 0, 1, 0),
+			inst(21, 0, 3, 2),
+			inst(22, 0, 2, 0),
+		];
+
+		assert_eq!(verify_code(code), 0);
+
+		let code = vec![
+			inst(68, 1, 0, 1),
+			inst(6, 0, 1, 0),
+			inst(21, 0, 1, 2),
+			inst(22, 0, 2, 0),
+		];
+
+		assert_eq!(verify_code(code), 0);
+	}
+
+	#[test]
+	fn verify_bad_jump() {
+		// jump -3 lands before the function, jump +1 into an aux word
+		let code = vec![inst_d(23, 0, -3), inst_d(23, 0, 1), inst(53, 0, 0, 0), 0];
+
+		assert_eq!(verify_code(code), 2);
+	}
+}
@@ -1,24 +1,64 @@
-use std::{ops::Range, sync::RwLock};
+use std::{io::Error, ops::Range, sync::Arc};
 
 use binaryninja::{
-	architecture::{ArchitectureExt, CoreArchitecture},
+	architecture::{ArchitectureExt, CoreArchitecture, Register as _},
 	binaryview::{BinaryView, BinaryViewBase, BinaryViewExt, Result as BResult},
 	custombinaryview::{
 		BinaryViewType, BinaryViewTypeBase, CustomBinaryView, CustomBinaryViewType, CustomView,
 		CustomViewBuilder,
 	},
+	function::Function as BnFunction,
 	rc::Ref,
 	section::{Section, Semantics},
 	segment::Segment,
 	symbol::{Symbol, SymbolType},
-	types::Type,
+	types::{FunctionParameter, Type, Variable, VariableSourceType},
 	Endianness,
 };
-use once_cell::sync::Lazy;
+use log::{debug, error, warn};
+use once_cell::sync::OnceCell;
+
+use crate::backend::{architecture::ARCH_NAME, associated::Register};
+
+use super::{
+	component::add_folders,
+	data::{Function, Module, Str, TypeKind, TypeTag},
+	error::{ParseError, Stage},
+	naming::infer_names,
+	parser::salvage_bytes,
+	persist,
+	registry::{self, Entry},
+	validate::{validate, Diagnostic},
+	verify::verify,
+};
+
+pub const VIEW_NAME: &str = "Luau";
+
+fn salvage(view: &BinaryView) -> Result<(Module, Option<ParseError>), ParseError> {
+	let buffer = view.read_buffer(0, view.len()).map_err(|_| {
+		let cause = Error::other("Failed to read buffer");
+
+		ParseError::new(Stage::Buffer, 0, cause)
+	})?;
+
+	salvage_bytes(buffer.get_data())
+}
 
-use super::{data::Module, parser::parse};
+// A cut off dump keeps a readable string table and most of its functions, which
+// a stray binary that happens to start with a valid version byte does not
+fn is_salvageable(len: usize, module: &Module) -> bool {
+	let string_list = &module.string_list().data;
+	let function_list = module.function_list();
 
-pub static MODULE: Lazy<RwLock<Module>> = Lazy::new(RwLock::default);
+	let num_text = string_list
+		.iter()
+		.filter(|v| std::str::from_utf8(&v.data).is_ok())
+		.count();
+
+	let parsed = function_list.range.end;
+
+	!function_list.data.is_empty() && num_text * 4 >= string_list.len() * 3 && parsed * 4 >= len * 3
+}
 
 pub struct Builder {
 	pub typ: BinaryViewType,
@@ -42,7 +82,25 @@ impl BinaryViewTypeBase for Builder {
 	}
 
 	fn is_valid_for(&self, data: &BinaryView) -> bool {
-		parse(data).is_ok()
+		match salvage(data) {
+			Ok((_, None)) => true,
+			Ok((module, Some(e))) if is_salvageable(data.len(), &module) => {
+				debug!("Found partial Luau module, {e}");
+
+				true
+			}
+			Err(e) if e.stage() == Stage::Header => {
+				debug!("Not a Luau module, {e}");
+
+				false
+			}
+			// Every opened file is probed, so rejections are not worth a warning
+			Ok((_, Some(e))) | Err(e) => {
+				debug!("Rejected Luau module, {e}");
+
+				false
+			}
+		}
 	}
 }
 
@@ -52,9 +110,30 @@ impl CustomBinaryViewType for Builder {
 		data: &BinaryView,
 		builder: CustomViewBuilder<'builder, Self>,
 	) -> BResult<CustomView<'builder>> {
-		let module = parse(data)?;
+		let args = match persist::load(data) {
+			Some((data, module)) => Args {
+				data,
+				module,
+				is_saved: true,
+			},
+			// Opening a file explicitly as Luau salvages whatever parses, however little
+			None => {
+				let (module, error) =
+					salvage(data).map_err(|e| error!("Could not open Luau module, {e}"))?;
+
+				if let Some(e) = error {
+					warn!("Recovered partial Luau module, {e}");
+				}
+
+				Args {
+					data: data.read_vec(0, data.len()).into(),
+					module,
+					is_saved: false,
+				}
+			}
+		};
 
-		builder.create::<View>(data, module)
+		builder.create::<View>(data, args)
 	}
 }
 
@@ -62,40 +141,178 @@ fn to_range_u64(old: Range<usize>) -> Range<u64> {
 	old.start as u64..old.end as u64
 }
 
+fn to_view_range(base: u64, old: Range<usize>) -> Range<u64> {
+	base + old.start as u64..base + old.end as u64
+}
+
+fn new_luau_type(tag: TypeTag, module: &Module) -> Ref<Type> {
+	let base = match tag.kind {
+		TypeKind::Number => Type::float(8),
+		TypeKind::Boolean => Type::bool(),
+		_ => Type::int(8, false),
+	};
+
+	let name = match tag.kind {
+		TypeKind::Tagged(index) => module
+			.userdata_name(index)
+			.map_or_else(|| tag.kind.name().into(), Str::to_string_lossy),
+		_ => tag.kind.name().into(),
+	};
+
+	let suffix = if tag.is_optional { "?" } else { "" };
+
+	Type::named_type_from_type(format!("{name}{suffix}"), &base)
+}
+
+fn new_register_var(register: u8) -> Variable {
+	let storage = Register::Local(register).id();
+
+	Variable::new(
+		VariableSourceType::RegisterVariableSourceType,
+		0,
+		storage.into(),
+	)
+}
+
+fn add_signature(func_bn: &BnFunction, func: &Function, module: &Module) {
+	let any = TypeTag {
+		kind: TypeKind::Any,
+		is_optional: false,
+	};
+
+	let type_list = func.type_info().map_or(&[][..], |v| &v.param_list);
+	let param_list: Vec<_> = (0..func.num_param())
+		.map(|i| {
+			let name = func
+				.param_at(i)
+				.and_then(|local| module.string_at(local.name))
+				.map_or_else(|| format!("arg{i}"), |name| name.to_string_lossy().into());
+
+			let tag = type_list.get(usize::from(i)).copied().unwrap_or(any);
+
+			let location = new_register_var(i);
+
+			FunctionParameter::new(new_luau_type(tag, module), name, Some(location))
+		})
+		.collect();
+
+	let any = new_luau_type(any, module);
+
+	func_bn.set_auto_type(&Type::function(&*any, &param_list, func.is_vararg()));
+
+	if func.line_defined() != 0 {
+		func_bn.set_comment(format!("defined at line {}", func.line_defined()));
+	}
+}
+
+fn add_local_names(func_bn: &BnFunction, func: &Function, module: &Module) {
+	let any = new_luau_type(
+		TypeTag {
+			kind: TypeKind::Any,
+			is_optional: false,
+		},
+		module,
+	);
+
+	for register in func.num_param()..func.max_stack_size() {
+		let name = func
+			.register_name(register)
+			.and_then(|name| module.string_at(name));
+
+		if let Some(name) = name {
+			let var = new_register_var(register);
+
+			func_bn.create_user_var(&var, &*any, name.to_string_lossy().into_owned(), false);
+		}
+	}
+}
+
+fn add_line_comments(func_bn: &BnFunction, func: &Function, base: u64) {
+	let mut last = None;
+
+	for offset in func.code().step_by(4) {
+		let offset = offset as u64;
+		let line = func.line_at(offset);
+
+		if line == last {
+			continue;
+		}
+
+		if let Some(line) = line {
+			func_bn.set_comment_at(base + offset, format!("line {line}"));
+		}
+
+		last = line;
+	}
+}
+
+pub struct Args {
+	data: Box<[u8]>,
+	module: Module,
+	// Set when the module came from the database, which then has nothing new to store
+	is_saved: bool,
+}
+
 pub struct View {
 	view: Ref<BinaryView>,
+	entry: OnceCell<Arc<Entry>>,
 }
 
 impl View {
-	fn add_string_section(&self, range: Range<usize>) {
+	// Every address in the view is a file offset moved up to the base of its module
+	fn base(&self) -> u64 {
+		self.entry.get().map_or(0, |entry| entry.base())
+	}
+
+	fn add_data_section(&self, name: &str, range: Range<usize>) {
 		if range.is_empty() {
 			return;
 		}
 
-		let range = to_range_u64(range);
+		let view_range = to_view_range(self.base(), range.clone());
 
 		self.add_segment(
-			Segment::new(range.clone())
-				.parent_backing(range.clone())
+			Segment::new(view_range.clone())
+				.parent_backing(to_range_u64(range))
 				.contains_data(true)
 				.readable(true)
 				.is_auto(true),
 		);
 
-		self.add_section(Section::new("string_list", range).semantics(Semantics::ReadOnlyData));
+		self.add_section(Section::new(name, view_range).semantics(Semantics::ReadOnlyData));
+	}
+
+	fn add_compile_error(&self, compile_error: &Str) {
+		let plat = self.default_platform().unwrap();
+		let range = compile_error.range.clone();
+		let addr = self.base() + range.start as u64;
+
+		self.add_data_section("compile_error", range.clone());
+
+		let sym = Symbol::new(SymbolType::Data, "compile_error", addr).create();
+		let typ = &*Type::array(&*Type::char(), range.len() as u64);
+
+		self.define_auto_symbol_with_type(&sym, &plat, typ)
+			.expect("Failed to define symbol");
+
+		warn!(
+			"Luau module is a compile error, {}",
+			compile_error.to_string_lossy()
+		);
 	}
 
-	fn add_string_data(&self, data: &[Range<usize>]) {
+	fn add_string_data(&self, data: &[Str]) {
 		if data.is_empty() {
 			return;
 		}
 
 		let plat = self.default_platform().unwrap();
 		let byte = &*Type::char();
+		let base = self.base();
 
-		for (i, range) in data.iter().enumerate() {
+		for (i, Str { range, .. }) in data.iter().enumerate() {
 			let name = format!("str_{i}");
-			let sym = Symbol::new(SymbolType::Data, name, range.start as u64).create();
+			let sym = Symbol::new(SymbolType::Data, name, base + range.start as u64).create();
 
 			let typ = &*Type::array(byte, range.len() as u64);
 
@@ -105,11 +322,11 @@ impl View {
 	}
 
 	fn add_function_segment(&self, position: Range<usize>) {
-		let position = to_range_u64(position);
+		let view_range = to_view_range(self.base(), position.clone());
 
 		self.add_segment(
-			Segment::new(position.clone())
-				.parent_backing(position)
+			Segment::new(view_range)
+				.parent_backing(to_range_u64(position))
 				.contains_code(true)
 				.contains_data(true)
 				.readable(true)
@@ -123,7 +340,7 @@ impl View {
 			return;
 		}
 
-		let range = to_range_u64(code);
+		let range = to_view_range(self.base(), code);
 
 		self.add_section(
 			Section::new(format!("code_{index}"), range)
@@ -137,7 +354,7 @@ impl View {
 			return;
 		}
 
-		let range = to_range_u64(constant);
+		let range = to_view_range(self.base(), constant);
 
 		self.add_section(
 			Section::new(format!("data_{index}"), range)
@@ -146,18 +363,48 @@ impl View {
 		);
 	}
 
-	fn add_alias_for_function(&self, name: usize, data: &[Range<usize>], start: u64) {
-		let range = match name.checked_sub(1).and_then(|i| data.get(i)) {
-			Some(range) => range,
-			None => return,
-		};
+	// Nested closures carry the names of their parents, as in `main > onTouched > callback`
+	fn add_alias_for_function(&self, index: usize, module: &Module, start: u64) {
+		let name = module.function_name(index);
+		let full_name = module.qualified_name(index);
 
-		let raw = self.view.read_vec(range.start as u64, range.len());
-		let name = String::from_utf8_lossy(&raw);
-		let symbol = Symbol::new(SymbolType::Function, name.as_ref(), start).create();
+		let symbol = Symbol::new(SymbolType::Function, name.as_ref(), start)
+			.short_name(name.as_ref())
+			.full_name(full_name.as_str())
+			.create();
 
 		self.define_auto_symbol(&symbol);
 	}
+
+	fn add_diagnostics(&self, list: &[Diagnostic]) {
+		if list.is_empty() {
+			return;
+		}
+
+		let tag_type = self.create_tag_type("Luau Diagnostic", "⚠");
+		let base = self.base();
+
+		for diagnostic in list {
+			debug!("{diagnostic}");
+
+			self.add_tag(
+				base + diagnostic.offset as u64,
+				&tag_type,
+				diagnostic.to_string(),
+				false,
+			);
+		}
+
+		warn!("Luau module has {} diagnostics", list.len());
+	}
+}
+
+impl Drop for View {
+	fn drop(&mut self) {
+		if let Some(entry) = self.entry.get() {
+			registry::remove(entry);
+		}
+	}
 }
 
 impl AsRef<BinaryView> for View {
@@ -168,7 +415,9 @@ impl AsRef<BinaryView> for View {
 
 impl BinaryViewBase for View {
 	fn entry_point(&self) -> u64 {
-		0
+		let entry_point = self.entry.get().and_then(|v| v.module().entry_point());
+
+		self.base() + entry_point.unwrap_or_default()
 	}
 
 	fn default_endianness(&self) -> Endianness {
@@ -181,29 +430,66 @@ impl BinaryViewBase for View {
 }
 
 unsafe impl CustomBinaryView for View {
-	type Args = Module;
+	type Args = Args;
 
 	fn new(handle: &BinaryView, _args: &Self::Args) -> BResult<Self> {
 		let view = handle.to_owned();
 
-		Ok(Self { view })
+		Ok(Self {
+			view,
+			entry: OnceCell::new(),
+		})
 	}
 
 	fn init(&self, args: Self::Args) -> BResult<()> {
-		let arch = CoreArchitecture::by_name("luau").ok_or(())?;
+		let parent = self.parent_view().ok();
+		let base = parent
+			.as_ref()
+			.and_then(|parent| persist::load_base(parent))
+			.unwrap_or_else(registry::new_base);
+
+		let arch = CoreArchitecture::by_name(ARCH_NAME).ok_or(())?;
 		let plat = arch.standalone_platform().ok_or(())?;
 
 		self.set_default_arch(&arch);
 		self.set_default_platform(&plat);
 
-		let str_list = args.string_list();
+		let Args {
+			data,
+			mut module,
+			is_saved,
+		} = args;
+
+		if !is_saved {
+			let name_map = infer_names(&data, &module);
+
+			module.set_inferred_names(name_map);
+		}
+
+		let entry = registry::insert(base, data, module);
+
+		self.entry.set(entry.clone()).map_err(drop)?;
+
+		let module = entry.module();
+
+		if let Some(parent) = parent.filter(|_| !is_saved) {
+			persist::store(&parent, base, entry.data(), module);
+		}
+
+		if let Some(compile_error) = module.compile_error() {
+			self.add_compile_error(compile_error);
 
-		self.add_string_section(str_list.range.clone());
+			return Ok(());
+		}
+
+		let str_list = module.string_list();
+
+		self.add_data_section("string_list", str_list.range.clone());
 		self.add_string_data(&str_list.data);
 
-		for (i, func) in args.function_list().data.iter().enumerate() {
+		for (i, func) in module.function_list().data.iter().enumerate() {
 			let constant = func.constant_list().range.clone();
-			let inst = func.code().start as u64;
+			let inst = base + func.code().start as u64;
 
 			self.add_function_segment(func.position());
 
@@ -211,12 +497,35 @@ unsafe impl CustomBinaryView for View {
 			self.add_constant_section(i, constant);
 
 			self.add_auto_function(&plat, inst);
-			self.add_alias_for_function(func.name(), &str_list.data, inst);
+			self.add_alias_for_function(i, module, inst);
+
+			if let Ok(func_bn) = self.function_at(&plat, inst) {
+				add_signature(&func_bn, func, module);
+				add_local_names(&func_bn, func, module);
+				add_line_comments(&func_bn, func, base);
+			}
+		}
+
+		if let Some(entry_point) = module.entry_point() {
+			self.add_entry_point(&plat, base + entry_point);
 		}
 
-		self.add_entry_point(&plat, args.entry_point());
+		// Folders are saved with the database, so only a fresh module needs them
+		if !is_saved {
+			add_folders(&self.view, module, base);
+		}
+
+		let mut diagnostic_list = validate(module);
+
+		diagnostic_list.extend(verify(entry.data(), module));
 
-		*MODULE.write().unwrap() = args;
+		self.add_diagnostics(&diagnostic_list);
+
+		if !module.is_complete() {
+			let len = module.function_list().data.len();
+
+			warn!("Luau module is incomplete, recovered {len} functions");
+		}
 
 		Ok(())
 	}
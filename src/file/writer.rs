@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+
+use super::{
+	data::{Function, Module, Str, Value},
+	error::WriteError,
+	parser::TypeConstant,
+};
+
+pub type Buffer = Vec<u8>;
+
+pub fn write_u8(b: &mut Buffer, value: u8) {
+	b.push(value);
+}
+
+pub fn write_u32(b: &mut Buffer, value: u32) {
+	b.extend_from_slice(&value.to_le_bytes());
+}
+
+pub fn write_u64(b: &mut Buffer, value: u64) {
+	b.extend_from_slice(&value.to_le_bytes());
+}
+
+pub fn write_any_size(b: &mut Buffer, mut value: usize) {
+	loop {
+		let v = (value & 0x7F) as u8;
+
+		value >>= 7;
+
+		if value == 0 {
+			write_u8(b, v);
+
+			break;
+		}
+
+		write_u8(b, v | 0x80);
+	}
+}
+
+fn write_list_of<T, W>(b: &mut Buffer, list: &[T], write: W)
+where
+	W: Fn(&mut Buffer, &T),
+{
+	write_any_size(b, list.len());
+
+	for value in list {
+		write(b, value);
+	}
+}
+
+pub fn write_sized_data(b: &mut Buffer, data: &[u8]) {
+	write_any_size(b, data.len());
+
+	b.extend_from_slice(data);
+}
+
+pub fn write_constant(b: &mut Buffer, value: &Value) {
+	match value {
+		Value::Nil => write_u8(b, TypeConstant::Nil as u8),
+		Value::False | Value::True => {
+			write_u8(b, TypeConstant::Boolean as u8);
+			write_u8(b, matches!(value, Value::True).into());
+		}
+		Value::Number(data) => {
+			write_u8(b, TypeConstant::Number as u8);
+			write_u64(b, data.to_bits());
+		}
+		Value::String(index) => {
+			write_u8(b, TypeConstant::String as u8);
+			write_any_size(b, *index);
+		}
+		Value::Import(data) => {
+			write_u8(b, TypeConstant::Import as u8);
+			write_u32(b, *data);
+		}
+		Value::Table(key_list) => {
+			write_u8(b, TypeConstant::Table as u8);
+			write_list_of(b, key_list, |b, v| write_any_size(b, *v));
+		}
+		Value::Closure(index) => {
+			write_u8(b, TypeConstant::Closure as u8);
+			write_any_size(b, *index);
+		}
+		Value::Vector(data) => {
+			write_u8(b, TypeConstant::Vector as u8);
+
+			for value in data {
+				write_u32(b, value.to_bits());
+			}
+		}
+	}
+}
+
+pub struct Writer<'a> {
+	data: &'a [u8],
+	module: &'a Module,
+	string_map: HashMap<usize, Box<[u8]>>,
+	number_map: HashMap<(usize, usize), f64>,
+}
+
+impl<'a> Writer<'a> {
+	pub fn new(data: &'a [u8], module: &'a Module) -> Self {
+		Self {
+			data,
+			module,
+			string_map: HashMap::new(),
+			number_map: HashMap::new(),
+		}
+	}
+
+	pub fn replace_string(&mut self, index: usize, data: &[u8]) -> Result<(), WriteError> {
+		if index >= self.module.string_list().data.len() {
+			return Err(WriteError::NoString(index));
+		}
+
+		self.string_map.insert(index, data.into());
+
+		Ok(())
+	}
+
+	pub fn replace_number(
+		&mut self,
+		function: usize,
+		constant: usize,
+		value: f64,
+	) -> Result<(), WriteError> {
+		let func = self
+			.module
+			.function_list()
+			.data
+			.get(function)
+			.ok_or(WriteError::NoFunction(function))?;
+
+		match func.constant_list().data.get(constant) {
+			Some(Value::Number(_)) => {}
+			Some(_) => return Err(WriteError::NotNumber(function, constant)),
+			None => return Err(WriteError::NoConstant(function, constant)),
+		}
+
+		self.number_map.insert((function, constant), value);
+
+		Ok(())
+	}
+
+	fn write_string_list(&self, b: &mut Buffer, list: &[Str]) {
+		write_any_size(b, list.len());
+
+		for (i, value) in list.iter().enumerate() {
+			let data = self.string_map.get(&i).unwrap_or(&value.data);
+
+			write_sized_data(b, data);
+		}
+	}
+
+	// Functions without edits are copied as they are, so unusual but valid encodings
+	// survive, while edited ones have only their constant list written again
+	fn write_function(&self, b: &mut Buffer, index: usize, func: &Function) {
+		let position = func.position();
+		let constant_list = func.constant_list();
+
+		if !self
+			.number_map
+			.keys()
+			.any(|&(function, _)| function == index)
+		{
+			b.extend_from_slice(&self.data[position]);
+
+			return;
+		}
+
+		b.extend_from_slice(&self.data[position.start..constant_list.range.start]);
+
+		write_any_size(b, constant_list.data.len());
+
+		for (i, value) in constant_list.data.iter().enumerate() {
+			match self.number_map.get(&(index, i)) {
+				Some(&number) => write_constant(b, &Value::Number(number)),
+				None => write_constant(b, value),
+			}
+		}
+
+		b.extend_from_slice(&self.data[constant_list.range.end..position.end]);
+	}
+
+	pub fn write(&self) -> Result<Vec<u8>, WriteError> {
+		let module = self.module;
+		let string_list = module.string_list();
+		let function_list = module.function_list();
+		let mut b = Buffer::with_capacity(self.data.len());
+
+		if let Some(compile_error) = module.compile_error() {
+			b.extend_from_slice(&self.data[..compile_error.range.start]);
+			b.extend_from_slice(&compile_error.data);
+
+			return Ok(b);
+		}
+
+		if !module.is_complete() {
+			return Err(WriteError::Incomplete);
+		}
+
+		b.extend_from_slice(&self.data[..string_list.range.start]);
+
+		if self.string_map.is_empty() {
+			b.extend_from_slice(&self.data[string_list.range.clone()]);
+		} else {
+			self.write_string_list(&mut b, &string_list.data);
+		}
+
+		let first = function_list
+			.data
+			.first()
+			.map_or(function_list.range.end, |func| func.position().start);
+
+		// Userdata types and the function count sit between the two lists
+		b.extend_from_slice(&self.data[string_list.range.end..first]);
+
+		for (i, func) in function_list.data.iter().enumerate() {
+			self.write_function(&mut b, i, func);
+		}
+
+		b.extend_from_slice(&self.data[function_list.range.end..]);
+
+		Ok(b)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::Writer;
+	use crate::file::{
+		data::Value,
+		error::WriteError,
+		parser::parse_bytes,
+		sample::{hello, SampleFunction},
+	};
+
+	fn round_trip(data: &[u8]) -> Vec<u8> {
+		let module = parse_bytes(data).unwrap();
+
+		Writer::new(data, &module).write().unwrap()
+	}
+
+	#[test]
+	fn write_unmodified() {
+		for version in [3, 4, 6] {
+			let data = hello(version).build();
+
+			assert_eq!(round_trip(&data), data);
+		}
+
+		let mut sample = hello(6);
+
+		sample.type_version = 3;
+
+		let data = sample.build();
+
+		assert_eq!(round_trip(&data), data);
+	}
+
+	#[test]
+	fn write_unusual_encoding() {
+		let mut sample = hello(6);
+
+		sample.function_list.push(SampleFunction {
+			has_var_info: true,
+			..SampleFunction::default()
+		});
+
+		let data = sample.build();
+
+		assert_eq!(round_trip(&data), data);
+	}
+
+	#[test]
+	fn write_compile_error() {
+		let data = b"\0:1: Incomplete statement: expected assignment or a function call";
+
+		assert_eq!(round_trip(data), data);
+	}
+
+	#[test]
+	fn write_edited() {
+		let data = hello(6).build();
+		let module = parse_bytes(&data).unwrap();
+		let mut writer = Writer::new(&data, &module);
+
+		writer
+			.replace_string(1, b"a much longer string than before")
+			.unwrap();
+		writer.replace_number(1, 3, 42.0).unwrap();
+
+		let data = writer.write().unwrap();
+		let module = parse_bytes(&data).unwrap();
+		let func = &module.function_list().data[1];
+
+		assert_eq!(
+			&*module.string_at(2).unwrap().data,
+			b"a much longer string than before"
+		);
+		assert!(matches!(func.constant_list().data[3], Value::Number(v) if v == 42.0));
+	}
+
+	#[test]
+	fn write_bad_edit() {
+		let data = hello(6).build();
+		let module = parse_bytes(&data).unwrap();
+		let mut writer = Writer::new(&data, &module);
+
+		assert_eq!(writer.replace_string(4, b"x"), Err(WriteError::NoString(4)));
+		assert_eq!(
+			writer.replace_number(2, 0, 1.0),
+			Err(WriteError::NoFunction(2))
+		);
+		assert_eq!(
+			writer.replace_number(1, 4, 1.0),
+			Err(WriteError::NoConstant(1, 4))
+		);
+		assert_eq!(
+			writer.replace_number(1, 0, 1.0),
+			Err(WriteError::NotNumber(1, 0))
+		);
+		assert_eq!(writer.write().unwrap(), data);
+	}
+}
@@ -1,21 +1,49 @@
+#[cfg(feature = "plugin")]
 use binaryninja::{
 	architecture::register_architecture, callingconvention::register_calling_convention,
-	custombinaryview::register_view_type,
+	command::register, custombinaryview::register_view_type,
 };
+#[cfg(feature = "plugin")]
+use log::LevelFilter;
 
-use backend::architecture::{Architecture, CallingConvention};
-use file::view::Builder;
+#[cfg(feature = "plugin")]
+use backend::architecture::{Architecture, CallingConvention, ARCH_NAME};
+#[cfg(feature = "plugin")]
+use file::{
+	export::{save_bytecode, save_with_number, save_with_string, SaveCommand},
+	view::{Builder, VIEW_NAME},
+};
 
+#[cfg(feature = "plugin")]
 mod backend;
-mod decoder;
-mod file;
+pub mod decoder;
+pub mod file;
 
+#[cfg(feature = "plugin")]
 #[no_mangle]
 pub extern "C" fn CorePluginInit() -> bool {
-	let arch = register_architecture("luau", Architecture::new);
+	binaryninja::logger::init(LevelFilter::Info).expect("Failed to set up logging");
+
+	let arch = register_architecture(ARCH_NAME, Architecture::new);
 
 	register_calling_convention(arch, "luau", CallingConvention);
-	register_view_type("Luau", "Roblox Luau", Builder::new);
+	register_view_type(VIEW_NAME, "Roblox Luau", Builder::new);
+
+	register(
+		"Luau\\Save Bytecode...",
+		"Write the module, including any patches, as Luau bytecode",
+		SaveCommand(save_bytecode),
+	);
+	register(
+		"Luau\\Save Bytecode With String...",
+		"Replace a string in the string table and write the module as Luau bytecode",
+		SaveCommand(save_with_string),
+	);
+	register(
+		"Luau\\Save Bytecode With Number...",
+		"Replace a number constant and write the module as Luau bytecode",
+		SaveCommand(save_with_number),
+	);
 
 	true
 }